@@ -0,0 +1,177 @@
+use barn::game::barn_context::BarnContext;
+use crate::game::camera::Camera;
+use crate::settings::{self, Settings, MAX_VOLUME};
+
+use sdl2::mixer::{self, Channel, Chunk, Group};
+
+use std::cell::RefCell;
+
+// Channel layout. Music gets two channels so one track can fade out while the
+// next fades in, and ambient loops get their own. The rest are pooled: menu
+// sounds and level sounds each take whichever channel in their group is free,
+// so overlapping pushes don't cut each other off.
+const MUSIC_CHANNELS: [i32; 2] = [0, 1];
+pub const AMBIENT_CHANNEL: i32 = 2;
+const RESERVED_CHANNELS: i32 = 3;
+const MENU_CHANNELS: (i32, i32) = (3, 6);
+const WORLD_CHANNELS: (i32, i32) = (7, 23);
+const MENU_GROUP: Group = Group(1);
+const WORLD_GROUP: Group = Group(2);
+
+// Fade time between tracks and when leaving a level, in milliseconds.
+pub const FADE_TIME: i32 = 800;
+
+// How far from the middle of the view a sound can still be heard, in pixels.
+const HEARING_RANGE: f32 = 700.0;
+
+pub const MENU_MUSIC: &str = "res/sound/laidback.mp3";
+
+// Every track, by name, for the music test in the options menu.
+pub const TRACKS: [(&str, &str); 2] = [
+    ("Laidback", MENU_MUSIC),
+    ("Ponder", "res/sound/ponder.wav"),
+];
+
+struct Mixer {
+    music: Option<String>,
+    music_channel: usize,
+    ambience: Option<String>,
+}
+
+thread_local! {
+    static MIXER: RefCell<Mixer> = RefCell::new(Mixer {
+        music: None,
+        music_channel: 0,
+        ambience: None,
+    });
+}
+
+// Set up the channel pools. Called once the audio device is open.
+pub fn init() {
+    mixer::allocate_channels(WORLD_CHANNELS.1 + 1);
+    mixer::reserve_channels(RESERVED_CHANNELS);
+    MENU_GROUP.add_channels_range(MENU_CHANNELS.0, MENU_CHANNELS.1);
+    WORLD_GROUP.add_channels_range(WORLD_CHANNELS.0, WORLD_CHANNELS.1);
+}
+
+// Mixer volume, out of 128, for one of the volume sliders scaled by the master volume.
+fn get_level(settings: &Settings, volume: u32) -> i32 {
+    if settings.mute {
+        0
+    } else {
+        (settings.master_volume * volume * 128 / (MAX_VOLUME * MAX_VOLUME)) as i32
+    }
+}
+
+pub fn apply_volumes(settings: &Settings) {
+    let music = get_level(settings, settings.music_volume);
+    let sfx = get_level(settings, settings.sfx_volume);
+    for channel in MUSIC_CHANNELS.iter() {
+        Channel(*channel).set_volume(music);
+    }
+    Channel(AMBIENT_CHANNEL).set_volume(sfx);
+    for channel in MENU_CHANNELS.0..=WORLD_CHANNELS.1 {
+        Channel(channel).set_volume(sfx);
+    }
+}
+
+// Play a sound on a free channel of the group, taking over the oldest one if
+// they're all busy.
+fn play_in_group(group: Group, chunk: &Chunk) -> Option<Channel> {
+    if !settings::is_sound_enabled() {
+        return None;
+    }
+    let channel = group.find_available().or_else(|| group.find_oldest())?;
+    channel.play(chunk, 0).ok()
+}
+
+// Menu and interface sounds.
+pub fn play_sound(context: &mut BarnContext, path: &str) -> Option<Channel> {
+    if !settings::is_sound_enabled() {
+        return None;
+    }
+    play_in_group(MENU_GROUP, context.load_sound(String::from(path)))
+}
+
+// Sounds made by things in the level, placed at the point they came from.
+// These are faded out when the level is left.
+pub fn play_world_sound(chunk: &Chunk, x: f32, y: f32, camera: &Camera) -> Option<Channel> {
+    let channel = play_in_group(WORLD_GROUP, chunk)?;
+    set_position(channel, x, y, camera);
+    Some(channel)
+}
+
+// Loop a sound in the level until it's stopped. Loops only take free channels
+// so they never cut off anything else.
+pub fn play_world_loop(chunk: &Chunk, x: f32, y: f32, camera: &Camera) -> Option<Channel> {
+    if !settings::is_sound_enabled() {
+        return None;
+    }
+    let channel = WORLD_GROUP.find_available()?.fade_in(chunk, -1, FADE_TIME).ok()?;
+    set_position(channel, x, y, camera);
+    Some(channel)
+}
+
+// Pan a sound towards the side of the view it's on and make it quieter the
+// further it is from the middle.
+pub fn set_position(channel: Channel, x: f32, y: f32, camera: &Camera) {
+    let dx = x - (camera.x + camera.width / 2) as f32;
+    let dy = y - (camera.y + camera.height / 2) as f32;
+    let pan = (dx / (camera.width / 2) as f32).max(-1.0).min(1.0);
+    let left = (255.0 * (1.0 - pan).min(1.0)) as u8;
+    let right = (255.0 * (1.0 + pan).min(1.0)) as u8;
+    let distance = ((dx * dx + dy * dy).sqrt() / HEARING_RANGE * 255.0).min(255.0) as u8;
+    let _ = channel.set_panning(left, right);
+    let _ = channel.set_distance(distance);
+}
+
+pub fn fade_out_world_sounds() {
+    WORLD_GROUP.fade_out(FADE_TIME);
+}
+
+// Loop a track, crossfading from whatever was playing. Does nothing if the
+// track is already on, so every state can ask for the music it wants.
+pub fn play_music(context: &mut BarnContext, path: &str) {
+    MIXER.with(|mixer| {
+        let mut mixer = mixer.borrow_mut();
+        if mixer.music.as_deref() == Some(path) {
+            return;
+        }
+        if mixer.music.is_some() {
+            Channel(MUSIC_CHANNELS[mixer.music_channel]).fade_out(FADE_TIME);
+        }
+        mixer.music_channel = 1 - mixer.music_channel;
+        let chunk = context.load_sound(String::from(path));
+        if Channel(MUSIC_CHANNELS[mixer.music_channel]).fade_in(chunk, -1, FADE_TIME).is_err() {
+            log::warn!("Could not play {}", path);
+        }
+        mixer.music = Some(String::from(path));
+    });
+}
+
+pub fn get_music() -> Option<String> {
+    MIXER.with(|mixer| mixer.borrow().music.clone())
+}
+
+// Loop a background sound on the ambient channel, or fade it out when given None.
+pub fn play_ambience(context: &mut BarnContext, path: Option<&str>) {
+    MIXER.with(|mixer| {
+        let mut mixer = mixer.borrow_mut();
+        if mixer.ambience.as_deref() == path {
+            return;
+        }
+        let channel = Channel(AMBIENT_CHANNEL);
+        match path {
+            Some(path) => {
+                let chunk = context.load_sound(String::from(path));
+                if channel.fade_in(chunk, -1, FADE_TIME).is_err() {
+                    log::warn!("Could not play {}", path);
+                }
+            }
+            None => {
+                channel.fade_out(FADE_TIME);
+            }
+        }
+        mixer.ambience = path.map(String::from);
+    });
+}
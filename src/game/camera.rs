@@ -7,8 +7,13 @@ pub struct Camera {
     pub miny: i32,
     pub maxx: i32,
     pub maxy: i32,
+    pub focus_x: f32,
+    pub focus_y: f32,
+    pub panning: bool,
 }
 
+const PAN_SPEED: f32 = 6.0;
+
 impl Camera {
     pub fn new() -> Camera {
         Camera {
@@ -20,6 +25,9 @@ impl Camera {
             miny: -300,
             maxx: 300,
             maxy: 300,
+            focus_x: 0.0,
+            focus_y: 0.0,
+            panning: false,
         }
     }
 
@@ -33,10 +41,49 @@ impl Camera {
     pub fn focus(&mut self, foc_x: i32, foc_y: i32) {
         self.x = foc_x - (self.width >> 1);
         self.y = foc_y - (self.height >> 1);
-        // TODO: Get camera constraints working.
         if self.x < self.minx { self.x = self.minx }
         else if self.x > self.maxx {self.x = self.maxx }
         if self.y < self.miny { self.y = self.miny }
         else if self.y > self.maxy { self.y = self.maxy }
     }
+
+    // Constrain the view to the given world area, centring it when it is smaller than the view.
+    pub fn set_bounds(&mut self, left: i32, top: i32, right: i32, bottom: i32) {
+        if right - left <= self.width {
+            self.minx = (left + right - self.width) / 2;
+            self.maxx = self.minx;
+        } else {
+            self.minx = left;
+            self.maxx = right - self.width;
+        }
+        if bottom - top <= self.height {
+            self.miny = (top + bottom - self.height) / 2;
+            self.maxy = self.miny;
+        } else {
+            self.miny = top;
+            self.maxy = bottom - self.height;
+        }
+    }
+
+    // Ease over to a new focus point instead of cutting to it, e.g. when swapping players.
+    pub fn pan(&mut self) {
+        self.panning = true;
+    }
+
+    pub fn follow(&mut self, foc_x: f32, foc_y: f32, dt: f32) {
+        if self.panning {
+            let dx = foc_x - self.focus_x;
+            let dy = foc_y - self.focus_y;
+            let t = (PAN_SPEED * dt).min(1.0);
+            self.focus_x += dx * t;
+            self.focus_y += dy * t;
+            if dx.abs() < 1.0 && dy.abs() < 1.0 {
+                self.panning = false;
+            }
+        } else {
+            self.focus_x = foc_x;
+            self.focus_y = foc_y;
+        }
+        self.focus(self.focus_x.round() as i32, self.focus_y.round() as i32);
+    }
 }
@@ -0,0 +1,187 @@
+use barn::input::keyboard_handler::KeyboardHandler;
+use crate::game::gamepad::{self, PadInput};
+use crate::game::mouse;
+use crate::settings;
+
+use sdl2::keyboard::Keycode;
+
+use std::collections::HashMap;
+
+// Everything the player can do, independent of the keys bound to it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Grab,
+    Rotate,
+    SwitchPlayer,
+    Restart,
+    Pause,
+    ToggleHud,
+    Confirm,
+    Back,
+    Options,
+    Challenge,
+}
+
+pub const ACTIONS: [Action; 14] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Grab,
+    Action::Rotate,
+    Action::SwitchPlayer,
+    Action::Restart,
+    Action::Pause,
+    Action::ToggleHud,
+    Action::Confirm,
+    Action::Back,
+    Action::Options,
+    Action::Challenge,
+];
+
+// Keys the rebinding screen listens for. Escape is left out as it cancels
+// listening, though it stays bound to Pause and Back by default.
+pub const BINDABLE_KEYS: [Keycode; 61] = [
+    Keycode::A, Keycode::B, Keycode::C, Keycode::D, Keycode::E, Keycode::F, Keycode::G,
+    Keycode::H, Keycode::I, Keycode::J, Keycode::K, Keycode::L, Keycode::M, Keycode::N,
+    Keycode::O, Keycode::P, Keycode::Q, Keycode::R, Keycode::S, Keycode::T, Keycode::U,
+    Keycode::V, Keycode::W, Keycode::X, Keycode::Y, Keycode::Z,
+    Keycode::Num0, Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4,
+    Keycode::Num5, Keycode::Num6, Keycode::Num7, Keycode::Num8, Keycode::Num9,
+    Keycode::Up, Keycode::Down, Keycode::Left, Keycode::Right,
+    Keycode::Space, Keycode::Return, Keycode::Tab, Keycode::Backspace,
+    Keycode::LShift, Keycode::RShift, Keycode::LCtrl, Keycode::RCtrl, Keycode::LAlt, Keycode::RAlt,
+    Keycode::Kp0, Keycode::Kp1, Keycode::Kp2, Keycode::Kp3, Keycode::Kp4,
+    Keycode::Kp5, Keycode::Kp6, Keycode::Kp7, Keycode::Kp8, Keycode::Kp9, Keycode::KpEnter,
+];
+
+impl Action {
+    // Name used for the action in the config file.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Grab => "grab",
+            Action::Rotate => "rotate",
+            Action::SwitchPlayer => "switch_player",
+            Action::Restart => "restart",
+            Action::Pause => "pause",
+            Action::ToggleHud => "toggle_hud",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::Options => "options",
+            Action::Challenge => "challenge",
+        }
+    }
+
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Grab => "Grab Block",
+            Action::Rotate => "Rotate Mirror",
+            Action::SwitchPlayer => "Switch Character",
+            Action::Restart => "Reset Puzzle",
+            Action::Pause => "Pause",
+            Action::ToggleHud => "Toggle HUD",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Options => "Options",
+            Action::Challenge => "Challenge Mode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|action| action.get_name() == name).copied()
+    }
+
+    // Controller buttons are fixed; only the keyboard can be rebound.
+    pub fn get_pad_inputs(&self) -> &'static [PadInput] {
+        match self {
+            Action::MoveUp => &[PadInput::Up],
+            Action::MoveDown => &[PadInput::Down],
+            Action::MoveLeft => &[PadInput::Left],
+            Action::MoveRight => &[PadInput::Right],
+            Action::Grab => &[PadInput::X],
+            Action::Rotate => &[PadInput::Y],
+            Action::SwitchPlayer => &[PadInput::RightShoulder],
+            Action::Restart => &[PadInput::Back],
+            Action::Pause => &[PadInput::Start],
+            Action::ToggleHud => &[PadInput::LeftShoulder],
+            Action::Confirm => &[PadInput::A],
+            Action::Back => &[PadInput::B],
+            Action::Options => &[PadInput::Y],
+            Action::Challenge => &[PadInput::X],
+        }
+    }
+
+    pub fn get_default_keys(&self) -> Vec<Keycode> {
+        match self {
+            Action::MoveUp => vec![Keycode::Up, Keycode::W],
+            Action::MoveDown => vec![Keycode::Down, Keycode::S],
+            Action::MoveLeft => vec![Keycode::Left, Keycode::A],
+            Action::MoveRight => vec![Keycode::Right, Keycode::D],
+            Action::Grab => vec![Keycode::Space],
+            Action::Rotate => vec![Keycode::E],
+            Action::SwitchPlayer => vec![Keycode::Tab],
+            Action::Restart => vec![Keycode::R],
+            Action::Pause => vec![Keycode::Escape, Keycode::Q],
+            Action::ToggleHud => vec![Keycode::H],
+            Action::Confirm => vec![Keycode::Return, Keycode::KpEnter],
+            Action::Back => vec![Keycode::Escape, Keycode::B],
+            Action::Options => vec![Keycode::O],
+            Action::Challenge => vec![Keycode::C],
+        }
+    }
+}
+
+pub fn get_default_bindings() -> HashMap<Action, Vec<Keycode>> {
+    ACTIONS.iter().map(|action| (*action, action.get_default_keys())).collect()
+}
+
+// Poll the controllers and mouse. Called once at the start of each state update.
+pub fn update() {
+    gamepad::update();
+    mouse::update();
+}
+
+// Whether any key or controller button bound to the action is held down.
+pub fn is_pressed(input: &KeyboardHandler, action: Action) -> bool {
+    if action.get_pad_inputs().iter().any(|pad| gamepad::is_held(*pad)) {
+        return true;
+    }
+    let settings = settings::SETTINGS.lock().unwrap();
+    match settings.bindings.get(&action) {
+        Some(keys) => keys.iter().any(|key| input.key_pressed(key)),
+        None => false,
+    }
+}
+
+// Whether any key or controller button bound to the action was pressed this frame.
+pub fn is_just_pressed(input: &KeyboardHandler, action: Action) -> bool {
+    if action.get_pad_inputs().iter().any(|pad| gamepad::is_just_held(*pad)) {
+        return true;
+    }
+    let settings = settings::SETTINGS.lock().unwrap();
+    match settings.bindings.get(&action) {
+        Some(keys) => keys.iter().any(|key| input.key_just_pressed(key)),
+        None => false,
+    }
+}
+
+// Bound keys as shown to the player, e.g. "Up, W".
+pub fn describe(action: Action) -> String {
+    let settings = settings::SETTINGS.lock().unwrap();
+    match settings.bindings.get(&action) {
+        Some(keys) if !keys.is_empty() => keys.iter().map(|key| key.name()).collect::<Vec<String>>().join(", "),
+        _ => String::from("-"),
+    }
+}
@@ -0,0 +1,131 @@
+use barn::graphics::barn_gfx::BarnGFX;
+use barn::game::barn_context::BarnContext;
+use barn::graphics::color::Color;
+use barn::graphics::fill_type::FillType;
+use crate::game::audio;
+use crate::game::camera::Camera;
+use crate::game::controls::{describe, get_default_bindings, ACTIONS, BINDABLE_KEYS};
+use crate::game::gamepad::{is_just_held, PadInput};
+use crate::settings;
+
+use sdl2::keyboard::Keycode;
+
+const MAX_BINDINGS: usize = 3;
+
+// Lists every action with its keys and lets the player rebind them. The menu
+// itself always answers to the arrow keys, enter, escape and the controller so
+// that a bad binding can't lock the player out.
+pub struct ControlsMenu {
+    pub selected_option: usize,
+    pub listening: bool,
+}
+
+impl ControlsMenu {
+    pub fn new() -> Self {
+        ControlsMenu {
+            selected_option: 0,
+            listening: false,
+        }
+    }
+
+    // Returns true once the player backs out of the menu.
+    pub fn update(&mut self, context: &mut BarnContext) -> bool {
+        let rows = ACTIONS.len() + 2;
+        if self.listening {
+            // Add the next key pressed to the action, dropping its oldest key when full.
+            if context.input.key_just_pressed(&Keycode::Escape) || is_just_held(PadInput::B) {
+                self.listening = false;
+            } else if let Some(key) = BINDABLE_KEYS.iter().find(|key| context.input.key_just_pressed(key)) {
+                let action = ACTIONS[self.selected_option];
+                settings::update(|settings| {
+                    let keys = settings.bindings.entry(action).or_insert_with(Vec::new);
+                    if !keys.contains(key) {
+                        if keys.len() == MAX_BINDINGS {
+                            keys.remove(0);
+                        }
+                        keys.push(*key);
+                    }
+                });
+                self.listening = false;
+            }
+            return false;
+        }
+
+        let prev_option = self.selected_option;
+        if context.input.key_just_pressed(&Keycode::Escape) || is_just_held(PadInput::B) {
+            return true;
+        } else if context.input.key_just_pressed(&Keycode::Down) || is_just_held(PadInput::Down) {
+            self.selected_option = (self.selected_option + 1) % rows;
+        } else if context.input.key_just_pressed(&Keycode::Up) || is_just_held(PadInput::Up) {
+            self.selected_option = (self.selected_option + rows - 1) % rows;
+        } else if (context.input.key_just_pressed(&Keycode::Backspace) || is_just_held(PadInput::X)) && self.selected_option < ACTIONS.len() {
+            let action = ACTIONS[self.selected_option];
+            settings::update(|settings| {
+                settings.bindings.insert(action, Vec::new());
+            });
+        } else if context.input.key_just_pressed(&Keycode::Return) || is_just_held(PadInput::A) {
+            if self.selected_option < ACTIONS.len() {
+                self.listening = true;
+            } else if self.selected_option == ACTIONS.len() {
+                settings::update(|settings| settings.bindings = get_default_bindings());
+            } else {
+                return true;
+            }
+        }
+        if prev_option != self.selected_option {
+            audio::play_sound(context, "res/sound/select.ogg");
+        }
+        false
+    }
+
+    pub fn draw(&self, context: &mut BarnContext, bgfx: &mut BarnGFX, camera: &Camera) {
+        let font = context.load_font(*settings::FONT_DETAILS);
+
+        // Render screen title.
+        bgfx.sdl.set_draw_color(Color::WHITE);
+        bgfx.sdl.draw_text("Controls", font,
+            camera.width as f32 / 2.0,
+            20.0,
+            3.0,
+            3.0,
+            true,
+            false);
+
+        for i in 0..ACTIONS.len() + 2 {
+            let (label, keys) = if i < ACTIONS.len() {
+                let keys = if self.listening && i == self.selected_option {
+                    String::from("Press a key (Esc cancels)")
+                } else {
+                    describe(ACTIONS[i])
+                };
+                (ACTIONS[i].get_label(), keys)
+            } else if i == ACTIONS.len() {
+                ("Reset Defaults", String::new())
+            } else {
+                ("Back", String::new())
+            };
+            let y = 90 + 30 * i as i32;
+            if i == self.selected_option {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+                bgfx.sdl.draw_rect(camera.width / 2 - 300, y, 600, 30, FillType::FILL, false);
+                bgfx.sdl.set_draw_color(Color::BLACK);
+            } else {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+            }
+            bgfx.sdl.draw_text(label, font,
+                (camera.width / 2 - 290) as f32,
+                y as f32 + 2.0,
+                1.5,
+                1.5,
+                false,
+                false);
+            bgfx.sdl.draw_text(&keys, font,
+                (camera.width / 2 + 20) as f32,
+                y as f32 + 2.0,
+                1.5,
+                1.5,
+                false,
+                false);
+        }
+    }
+}
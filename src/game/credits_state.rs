@@ -2,8 +2,9 @@ use barn::graphics::barn_gfx::BarnGFX;
 use barn::game::barn_context::BarnContext;
 use barn::game::state::State;
 use barn::graphics::color::Color;
-use barn::input::SdlKeycode;
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::controls::{self, describe, is_just_pressed, Action};
 use crate::game::start_menu_state::StartMenuState;
 use crate::settings;
 
@@ -13,12 +14,9 @@ pub struct CreditsState {
 
 impl State<BarnContext> for CreditsState {
     fn update(&mut self, context: &mut BarnContext, dt: f32) -> Option<Box<dyn State<BarnContext>>> {
-        if context.input.key_just_pressed(&SdlKeycode::Return) {
-            if settings::ENABLE_SOUND {
-                let back_fx = context.load_sound(String::from("res/sound/back.ogg"));
-                let channel = sdl2::mixer::Channel(2);
-                channel.play(back_fx, 0).unwrap();
-            }
+        controls::update();
+        if is_just_pressed(&context.input, Action::Confirm) || is_just_pressed(&context.input, Action::Back) {
+            audio::play_sound(context, "res/sound/back.ogg");
             
             return Some(Box::new(StartMenuState::new(2)));
         }
@@ -57,7 +55,7 @@ impl State<BarnContext> for CreditsState {
             true,
             false);
 
-        bgfx.sdl.draw_text("Press enter to return to the menu.", font, 
+        bgfx.sdl.draw_text(&format!("Press {} to return to the menu.", describe(Action::Confirm)), font, 
             0.0,
             550.0,
             2.0,
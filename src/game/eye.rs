@@ -1,6 +1,10 @@
 extern crate rand;
 
 use barn::graphics::barn_gfx::BarnGFX;
+use barn::graphics::SdlBlendType;
+use barn::graphics::color::Color;
+use barn::graphics::fill_type::FillType;
+use barn::math::vector2::Vector2;
 use crate::game::camera::Camera;
 use crate::game::tile::Tile;
 
@@ -9,6 +13,17 @@ use sdl2::render::Texture;
 
 use rand::Rng;
 
+const SIGHT_LINES: [(&str, f32, f32); 4] = [("left", -1.0, 0.0), ("right", 1.0, 0.0), ("down", 0.0, 1.0), ("up", 0.0, -1.0)];
+const MAX_REFLECTIONS: u32 = 16;
+const MAX_SIGHT: f32 = 4096.0;
+
+// An eye starting or stopping seeing its block.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EyeEvent {
+    Solved,
+    Unsolved,
+}
+
 pub struct Eye {
     pub direction: String,
     pub color: String,
@@ -23,54 +38,18 @@ pub struct Eye {
 }
 
 impl Eye {
-    pub fn update(&mut self, tiles: &mut Vec<Tile>, dt: f32) {
+    pub fn update(&mut self, tiles: &mut Vec<Tile>, dt: f32) -> Option<EyeEvent> {
         let mut rng = rand::thread_rng();
+        let was_solved = self.solved;
         self.solved = false;
-        let mut distance = -1.0;
         let mut isblock = false;
-        // Check for the closest block.
-        for tile in tiles.iter_mut() {
-            if tile.bb.origin.y == self.y as f32 && self.x as f32 > tile.bb.origin.x && (tile.isblock || tile.iswall) {
-                if distance == -1.0 || self.x as f32 - tile.bb.origin.x < distance {
-                    distance = self.x as f32 - tile.bb.origin.x;
-                    isblock = tile.isblock && tile.texture.contains(&self.color);
-                    self.direction = String::from("left");
-                }
-            }
-        }
-        if !isblock {
-            distance = -1.0;
-            for tile in tiles.iter_mut() {
-                if tile.bb.origin.y == self.y as f32 && (self.x as f32) < tile.bb.origin.x && (tile.isblock || tile.iswall) {
-                    if distance == -1.0 || tile.bb.origin.x - (self.x as f32) < distance {
-                        distance = tile.bb.origin.x - self.x as f32;
-                        isblock = tile.isblock && tile.texture.contains(&self.color);
-                        self.direction = String::from("right");
-                    }
-                }
-            }
-        }
-        if !isblock {
-            distance = -1.0;
-            for tile in tiles.iter_mut() {
-                if tile.bb.origin.x == self.x as f32 && (self.y as f32) < tile.bb.origin.y && (tile.isblock || tile.iswall) {
-                    if distance == -1.0 || tile.bb.origin.y - (self.y as f32) < distance {
-                        distance = tile.bb.origin.y - self.y as f32;
-                        isblock = tile.isblock && tile.texture.contains(&self.color);
-                        self.direction = String::from("up");
-                    }
-                }
-            }
-        }
-        if !isblock {
-            distance = -1.0;
-            for tile in tiles.iter_mut() {
-                if tile.bb.origin.x == self.x as f32 && self.y as f32 > tile.bb.origin.y && (tile.isblock || tile.iswall) {
-                    if distance == -1.0 || self.y as f32 - tile.bb.origin.y < distance {
-                        distance = self.y as f32- tile.bb.origin.y;
-                        isblock = tile.isblock && tile.texture.contains(&self.color);
-                        self.direction = String::from("down");
-                    }
+        // Look down each line of sight, following mirrors, for a block of our colour.
+        for (direction, dx, dy) in SIGHT_LINES.iter() {
+            if let Some(tile) = cast_ray(tiles, self.x as f32, self.y as f32, *dx, *dy).hit {
+                if tile.isblock && tile.color == self.color {
+                    isblock = true;
+                    self.direction = String::from(*direction);
+                    break;
                 }
             }
         }
@@ -103,7 +82,7 @@ impl Eye {
                 } else {
                     255
                 };
-            } else if self.direction == "up" {
+            } else if self.direction == "down" {
                 self.solved = true;
                 self.deltay = if self.deltay < 6.0 { self.deltay + dt * 100.0 } else { 6.0 };
                 self.deltax = 0.0;
@@ -112,7 +91,7 @@ impl Eye {
                 } else {
                     255
                 };
-            } else if self.direction == "down" {
+            } else if self.direction == "up" {
                 self.solved = true;
                 self.deltay = if self.deltay > -6.0 {
                     self.deltay - dt * 100.0
@@ -137,6 +116,47 @@ impl Eye {
                 0
             };
         }
+
+        match (was_solved, self.solved) {
+            (false, true) => Some(EyeEvent::Solved),
+            (true, false) => Some(EyeEvent::Unsolved),
+            _ => None,
+        }
+    }
+
+    // Each colour has its own pitch so the player can tell which eye reacted.
+    pub fn get_sound(&self, event: EyeEvent) -> String {
+        match event {
+            EyeEvent::Solved => format!("res/sound/eye_solved_{}.wav", self.color),
+            EyeEvent::Unsolved => format!("res/sound/eye_unsolved_{}.wav", self.color),
+        }
+    }
+
+    // Trace each line of sight as a faint beam, bending off mirrors.
+    pub fn draw_gaze(&self, tiles: &Vec<Tile>, camera: &mut Camera, bgfx: &mut BarnGFX) {
+        let color = if self.color == "red" {
+            Color::from_rgba(255, 0, 0, 80)
+        } else if self.color == "green" {
+            Color::from_rgba(0, 255, 0, 80)
+        } else {
+            Color::from_rgba(0, 0, 255, 80)
+        };
+        bgfx.sdl.set_draw_color(color);
+        bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+        let (half_width, half_height) = (self.width as f32 / 2.0, self.height as f32 / 2.0);
+        for (_, dx, dy) in SIGHT_LINES.iter() {
+            let path = cast_ray(tiles, self.x as f32, self.y as f32, *dx, *dy).path;
+            for segment in path.windows(2) {
+                let (start, end) = (segment[0], segment[1]);
+                bgfx.sdl.draw_rect(
+                    (start.x.min(end.x) + half_width) as i32 - 2 - camera.x,
+                    (start.y.min(end.y) + half_height) as i32 - 2 - camera.y,
+                    (start.x - end.x).abs() as u32 + 4,
+                    (start.y - end.y).abs() as u32 + 4,
+                    FillType::FILL,
+                    false);
+            }
+        }
     }
 
     pub fn draw_socket(
@@ -179,3 +199,63 @@ impl Eye {
             )));
     }
 }
+
+// What a line of sight ran into, and the points it passed through on the way
+// (its start, each mirror it bounced off and where it ended).
+pub struct Sight<'a> {
+    pub hit: Option<&'a Tile>,
+    pub path: Vec<Vector2>,
+}
+
+// Follow a line of sight from a tile position, bouncing off mirrors, and return
+// the first tile that blocks it.
+pub fn cast_ray(tiles: &Vec<Tile>, x: f32, y: f32, dx: f32, dy: f32) -> Sight<'_> {
+    let (mut x, mut y, mut dx, mut dy) = (x, y, dx, dy);
+    let mut path = vec![Vector2 { x: x, y: y }];
+    for _ in 0..MAX_REFLECTIONS {
+        let mut closest: Option<&Tile> = None;
+        let mut distance = -1.0;
+        for tile in tiles.iter() {
+            if !tile.blocks_sight() {
+                continue;
+            }
+            // Only tiles lined up with the ray and ahead of it are visible.
+            let offset_x = tile.bb.origin.x - x;
+            let offset_y = tile.bb.origin.y - y;
+            if (dx == 0.0 && offset_x != 0.0) || (dy == 0.0 && offset_y != 0.0) {
+                continue;
+            }
+            let dist = offset_x * dx + offset_y * dy;
+            if dist <= 0.0 {
+                continue;
+            }
+            // Prefer a block over the floor feature it sits on.
+            if distance == -1.0 || dist < distance || (dist == distance && tile.isblock) {
+                distance = dist;
+                closest = Some(tile);
+            }
+        }
+        match closest {
+            Some(mirror) if mirror.ismirror => {
+                x = mirror.bb.origin.x;
+                y = mirror.bb.origin.y;
+                path.push(mirror.bb.origin);
+                let (prev_dx, prev_dy) = (dx, dy);
+                if mirror.direction == "/" {
+                    dx = -prev_dy;
+                    dy = -prev_dx;
+                } else {
+                    dx = prev_dy;
+                    dy = prev_dx;
+                }
+            }
+            Some(tile) => {
+                path.push(tile.bb.origin);
+                return Sight { hit: closest, path: path };
+            }
+            None => break,
+        }
+    }
+    path.push(Vector2 { x: x + dx * MAX_SIGHT, y: y + dy * MAX_SIGHT });
+    Sight { hit: None, path: path }
+}
@@ -1,9 +1,14 @@
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::lighting::{Light, TORCH_RADIUS};
 use std::collections::HashMap;
 
 use barn::game::barn_context::BarnContext;
 use barn::graphics::barn_gfx::BarnGFX;
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use sdl2::mixer::Channel;
 
 use sdl2::rect::Rect;
 use sdl2::render::Texture;
@@ -18,6 +23,10 @@ pub struct Fire {
     pub frame: i32,
     pub active_animation: String,
     pub animations: HashMap<String, Vec<Rect>>,
+    // The crackle playing while the torch is on screen.
+    pub sound: Option<Channel>,
+    // Scales the light radius so the torchlight wavers.
+    pub flicker: f32,
 }
 
 impl Fire {
@@ -31,6 +40,8 @@ impl Fire {
             frame: Uniform::from(1..2).sample(&mut rand::thread_rng()),
             active_animation: String::from("flame"),
             animations: Fire::generate_animations(),
+            sound: None,
+            flicker: 1.0,
         }
     }
 
@@ -48,6 +59,10 @@ impl Fire {
     }
 
     pub fn update(&mut self) {
+        // Drift towards a new random brightness each frame.
+        let target = rand::thread_rng().gen_range(0.85, 1.05);
+        self.flicker += (target - self.flicker) * 0.2;
+
         // Update animation
         self.delay -= 1;
         if self.delay == 0 {
@@ -59,6 +74,40 @@ impl Fire {
         }
     }
 
+    pub fn get_light(&self) -> Light {
+        Light {
+            x: (self.x + self.width as i32 / 2) as f32,
+            y: (self.y + self.height as i32 / 2) as f32,
+            radius: TORCH_RADIUS * self.flicker,
+            casts_shadows: true,
+        }
+    }
+
+    // Crackle while on screen, panned to follow the torch as the view moves.
+    pub fn update_sound(&mut self, context: &mut BarnContext, camera: &mut Camera) {
+        let visible = camera.is_object_visible(self.x, self.y, self.width, self.height);
+        let (x, y) = ((self.x + self.width as i32 / 2) as f32, (self.y + self.height as i32 / 2) as f32);
+        // Forget the channel if something else stopped it.
+        if self.sound.map_or(false, |channel| !channel.is_playing()) {
+            self.sound = None;
+        }
+        match self.sound {
+            Some(channel) if visible => audio::set_position(channel, x, y, camera),
+            Some(_) => self.stop_sound(),
+            None if visible => {
+                let crackle = context.load_sound(String::from("res/sound/crackle.wav"));
+                self.sound = audio::play_world_loop(crackle, x, y, camera);
+            }
+            None => {}
+        }
+    }
+
+    pub fn stop_sound(&mut self) {
+        if let Some(channel) = self.sound.take() {
+            channel.fade_out(audio::FADE_TIME);
+        }
+    }
+
     pub fn draw(
         &mut self,
         context: &mut BarnContext,
@@ -4,86 +4,276 @@ use barn::game::barn_context::BarnContext;
 use barn::graphics::color::Color;
 use barn::graphics::fill_type::FillType;
 use barn::math::vector2::Vector2;
-use barn::math::bounding_box_2d::BoundingBox2D;
 use barn::game::state::State;
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::controls::{self, describe, is_just_pressed, is_pressed, Action};
 use crate::game::eye::Eye;
 use crate::game::fire::Fire;
+use crate::game::hud::Hud;
 use crate::game::level_select_state::LevelSelectState;
-use crate::game::physics::handle_collisions;
+use crate::game::lighting::{self, draw_lighting, Light};
+use crate::game::options_state::OptionsMenu;
+use crate::game::physics::{contains_point, find_block_in_reach, handle_collisions, handle_pull, handle_teleports, try_step, MoveEvent};
+use crate::game::mouse;
+use crate::game::pathfinding::{find_path, get_cell, get_cell_origin};
 use crate::game::player::Player;
-use crate::game::tile::Tile;
+use crate::game::start_menu_state::StartMenuState;
+use crate::game::signal::update_signals;
+use crate::game::tile::{direction_of, Tile, FALL_TIME};
+use crate::game::watcher::Watcher;
 use crate::settings;
 
-use sdl2::keyboard::Keycode;
-
+use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::time::Instant;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
 
 pub const TILE_WIDTH: u32 = 64;
 pub const TILE_HEIGHT: u32 = 64;
 
 pub const TILE_CHARS: [char; 6] = ['*', '5', '5', '6', '8', '9'];
+pub const ARROW_CHARS: [char; 4] = ['<', '>', '^', 'v'];
+pub const BLOCK_CHARS: [char; 3] = ['b', 'g', 'r'];
+pub const WEIGHT_CHARS: [char; 4] = ['1', '2', '3', '4'];
+pub const MAX_QUEUED_STEPS: usize = 3;
+pub const STEP_ACTIONS: [(Action, f32, f32); 4] = [
+    (Action::MoveUp, 0.0, -1.0),
+    (Action::MoveDown, 0.0, 1.0),
+    (Action::MoveLeft, -1.0, 0.0),
+    (Action::MoveRight, 1.0, 0.0),
+];
+pub const PAUSE_OPTIONS: [&str; 5] = ["Resume", "Restart", "Options", "Quit to Level Select", "Quit to Menu"];
 
 pub struct GameState/*<'a>*/ {
     pub level_path: String,
+    pub title: String,
+    pub metadata: HashMap<String, String>,
     pub won: bool,
-    pub time: Instant,
+    pub failed: bool,
+    pub paused: bool,
+    pub confirming: bool,
+    pub pause_option: usize,
+    pub options: Option<OptionsMenu>,
+    pub elapsed: f32,
     pub time_str: String,
-    pub moves: u32,
+    pub steps: u32,
+    pub pushes: u32,
+    pub move_limit: Option<u64>,
+    pub time_limit: Option<u64>,
     pub flames: Vec<Fire>,
     pub tiles: Vec<Tile>,
     pub blocks: Vec<Tile>,
     pub eyes: Vec<Eye>,
-    pub player: Player,
+    pub watchers: Vec<Watcher>,
+    pub players: Vec<Player>,
+    pub active_player: usize,
+    pub step_queue: VecDeque<Vector2>,
+    pub held_step: Option<(Action, Vector2)>,
+    pub repeat_timer: f32,
     pub camera: Camera,
+    pub hud: Hud,
 }
 
 impl State<BarnContext> for GameState {
     fn update(&mut self, context: &mut BarnContext, dt: f32) -> Option<Box<dyn State<BarnContext>>> {
-        if context.input.key_just_pressed(&Keycode::R) {
+        controls::update();
+        if self.paused {
+            return self.update_pause_menu(context);
+        }
+
+        if is_just_pressed(&context.input, Action::Restart) {
             self.load_level(String::from(&self.level_path), context);
-        } else if context.input.key_just_pressed(&Keycode::Q) {
-            return Some(Box::new(LevelSelectState::new(0)));
+        } else if is_just_pressed(&context.input, Action::Rotate) {
+            self.rotate_mirror();
+        } else if is_just_pressed(&context.input, Action::Pause) {
+            if self.won || self.failed {
+                // There is no progress left to lose.
+                return Some(Box::new(LevelSelectState::new(0)));
+            }
+            self.paused = true;
+            self.confirming = false;
+            self.pause_option = 0;
+            self.options = None;
+            return None;
+        } else if is_just_pressed(&context.input, Action::ToggleHud) {
+            self.hud.toggle();
         }
 
         for fire in self.flames.iter_mut() {
             fire.update();
+            fire.update_sound(context, &mut self.camera);
         }
 
+        // Entities update in a fixed order: blocks finish their moves, the eyes judge
+        // the board, the player moves and pushes, the level reacts to where everything
+        // ended up and finally the watchers patrol and look for the player.
         // TODO: Refactor this.
         let new_tiles = &mut self.tiles.to_vec();
         for tile in self.tiles.iter_mut() {
-            if tile.has_moved() {
-                self.moves += 1;
-            }
-            let move_fx = context.load_sound(String::from("res/sound/push.ogg"));
-            tile.update(new_tiles, move_fx, dt);
+            let move_fx = context.load_sound(tile.get_push_sound());
+            tile.update(new_tiles, move_fx, &self.camera, dt);
         }
 
-        // Check if the puzzle has been solved.
-        self.won = true;
+        // Check if the puzzle has been solved. Eyes that can already see their
+        // block when the level starts stay quiet.
+        let was_won = self.won;
+        self.won = !self.failed;
         for eye in self.eyes.iter_mut() {
-            eye.update(&mut self.tiles, dt);
+            if let Some(event) = eye.update(&mut self.tiles, dt) {
+                if self.elapsed > 0.0 {
+                    let fx = context.load_sound(eye.get_sound(event));
+                    audio::play_world_sound(fx,
+                        (eye.x + eye.width as i32 / 2) as f32,
+                        (eye.y + eye.height as i32 / 2) as f32,
+                        &self.camera);
+                }
+            }
             if !eye.solved {
                 self.won = false;
             }
         }
-        if !self.won {
-            // Update the player.
-            self.player.update(&mut context.input, dt);
-            let move_fx = context.load_sound(String::from("res/sound/push.ogg"));
-            handle_collisions(&mut self.player, &mut self.tiles, move_fx, dt);
-            self.camera.focus(
-                self.player.pos.x as i32 + self.player.width as i32 / 2,
-                self.player.pos.y as i32 + self.player.height as i32 / 2,
-            );
+        if self.won && !was_won {
+            audio::play_sound(context, "res/sound/solved.wav");
+        }
+        if self.failed {
+            if is_just_pressed(&context.input, Action::Confirm) {
+                return Some(Box::new(LevelSelectState::new(0)));
+            }
+        } else if !self.won {
+            self.elapsed += dt;
+            if is_just_pressed(&context.input, Action::SwitchPlayer) && self.players.len() > 1 {
+                self.players[self.active_player].stop();
+                self.players[self.active_player].path.clear();
+                self.step_queue.clear();
+                self.held_step = None;
+                self.repeat_timer = 0.0;
+                self.active_player = (self.active_player + 1) % self.players.len();
+                self.camera.pan();
+            }
+
+            // Update the controlled player; the others stand still and get in the way.
+            let mut player = self.players.remove(self.active_player);
+            let grabbed = if self.metadata.contains_key("pull") && is_pressed(&context.input, Action::Grab) {
+                find_block_in_reach(&mut player, &self.tiles)
+            } else {
+                None
+            };
+            player.grabbing = grabbed.is_some();
+            let mut events: Vec<MoveEvent> = Vec::new();
+            let prev_cell = get_cell(player.get_center());
+            let prev_pos = player.pos;
+
+            // Clicking a floor tile walks the player there, around anything in the way.
+            if mouse::is_clicked() {
+                let (mouse_x, mouse_y) = mouse::get_position();
+                let target = get_cell(Vector2 { x: (mouse_x + self.camera.x) as f32, y: (mouse_y + self.camera.y) as f32 });
+                if let Some(cells) = find_path(&self.tiles, &self.players, prev_cell, target) {
+                    player.path = Some(prev_cell).into_iter().chain(cells)
+                        .map(|cell| player.get_tile_position(get_cell_origin(cell), TILE_WIDTH, TILE_HEIGHT))
+                        .collect();
+                }
+            }
+
+            let allow_chain = self.metadata.contains_key("chain");
+            let current = settings::get();
+            if current.grid_movement {
+                // Each press moves exactly one tile; presses made mid-step wait their turn.
+                for (action, dx, dy) in STEP_ACTIONS.iter() {
+                    if is_just_pressed(&context.input, *action) {
+                        let step = Vector2 { x: dx * TILE_WIDTH as f32, y: dy * TILE_HEIGHT as f32 };
+                        if self.step_queue.len() < MAX_QUEUED_STEPS {
+                            self.step_queue.push_back(step);
+                        }
+                        self.held_step = Some((*action, step));
+                        self.repeat_timer = current.repeat_delay as f32 / 1000.0;
+                    }
+                }
+                // Holding a direction keeps stepping once the queue has drained. Letting go
+                // of the latest direction falls back to any other that is still held.
+                if let Some((action, _)) = self.held_step {
+                    if !is_pressed(&context.input, action) {
+                        self.held_step = STEP_ACTIONS.iter()
+                            .find(|(action, _, _)| is_pressed(&context.input, *action))
+                            .map(|(action, dx, dy)| {
+                                (*action, Vector2 { x: dx * TILE_WIDTH as f32, y: dy * TILE_HEIGHT as f32 })
+                            });
+                    }
+                }
+                if let Some((_, step)) = self.held_step {
+                    self.repeat_timer -= dt;
+                    if self.repeat_timer <= 0.0 && self.step_queue.is_empty() {
+                        self.step_queue.push_back(step);
+                        self.repeat_timer = current.repeat_interval as f32 / 1000.0;
+                    }
+                }
+                // Wait for pushed blocks to settle so queued steps play out the same every time.
+                let settled = !self.tiles.iter().any(|tile| tile.moving || tile.falling > 0.0);
+                if player.path.is_empty() && settled {
+                    if let Some(step) = self.step_queue.pop_front() {
+                        if !try_step(&mut player, &self.players, &mut self.tiles, step, allow_chain, grabbed.is_some(), &mut events) {
+                            player.face(direction_of(step));
+                        }
+                    }
+                }
+                player.update_grid(dt);
+                player.pos += player.vel;
+                player.vel = Vector2::ZERO;
+            } else {
+                player.update(&mut context.input, dt);
+                if let Some(block) = grabbed {
+                    handle_pull(&mut player, &self.players, &mut self.tiles, block, &mut events, dt);
+                }
+                handle_collisions(&mut player, &self.players, &mut self.tiles, allow_chain, &mut events, dt);
+            }
+            // Give up on a path once something moves into the way.
+            if !player.path.is_empty() && player.pos == prev_pos {
+                player.path.clear();
+            }
+            // Walking into a new tile is a step; being teleported is not.
+            if get_cell(player.get_center()) != prev_cell {
+                events.push(MoveEvent::Step);
+            }
+            handle_teleports(&mut player, &self.players, &mut self.tiles);
+            self.players.insert(self.active_player, player);
+            for event in events {
+                match event {
+                    MoveEvent::Step => self.steps += 1,
+                    MoveEvent::Push => self.pushes += 1,
+                }
+            }
+
+            update_signals(&mut self.players, &mut self.tiles);
+            self.update_pits(dt);
+
+            // Being spotted by a watcher sends the player back to the start.
+            for watcher in self.watchers.iter_mut() {
+                watcher.update(&self.tiles, dt);
+            }
+            let mut spotted = false;
+            for watcher in self.watchers.iter_mut() {
+                for player in self.players.iter_mut() {
+                    spotted = spotted || watcher.can_see(player, &self.tiles);
+                }
+            }
+            if spotted {
+                self.load_level(String::from(&self.level_path), context);
+                return None;
+            }
+
+            let center = self.players[self.active_player].get_center();
+            self.camera.follow(center.x, center.y, dt);
+
+            // Going over either limit ends the attempt.
+            let over_moves = self.move_limit.map_or(false, |limit| self.pushes as u64 > limit);
+            let over_time = self.time_limit.map_or(false, |limit| self.elapsed as u64 >= limit);
+            self.failed = over_moves || over_time;
         } else {
             if self.time_str == "" {
-                let elapsed_time = self.time.elapsed().as_secs();
-                self.time_str = elapsed_time.to_string();
+                self.time_str = (self.elapsed as u64).to_string();
             }
-            if context.input.key_just_pressed(&Keycode::Return) {
+            if is_just_pressed(&context.input, Action::Confirm) {
                 return Some(Box::new(LevelSelectState::new(0)));
             }
         }
@@ -116,8 +306,9 @@ impl State<BarnContext> for GameState {
             }
         }
         let tex_shadow = context.load_texture(String::from("res/img/drop_shadow.png"));
-        self.player
-            .draw_shadow(tex_shadow, &mut self.camera, bgfx);
+        for player in self.players.iter_mut() {
+            player.draw_shadow(tex_shadow, &mut self.camera, bgfx);
+        }
         for tile in &mut self.tiles {
             if (tile.isblock || tile.iswall)
                 && self
@@ -134,6 +325,11 @@ impl State<BarnContext> for GameState {
                 );
             }
         }
+        if settings::get().show_gaze {
+            for eye in self.eyes.iter() {
+                eye.draw_gaze(&self.tiles, &mut self.camera, bgfx);
+            }
+        }
         // Render menu eyes.
         for eye in self.eyes.iter_mut() {
             let socket_tex =  context.load_texture(String::from("res/img/socket.png"));
@@ -149,11 +345,70 @@ impl State<BarnContext> for GameState {
             };
             eye.draw_iris(tex_pupil, &mut self.camera, bgfx);
         }
+        for watcher in self.watchers.iter_mut() {
+            watcher.draw(context, &mut self.camera, bgfx);
+        }
         let tex_player = context.load_texture(String::from("res/img/player.png"));
-        self.player.draw(tex_player, &mut self.camera, bgfx);
+        for (i, player) in self.players.iter_mut().enumerate() {
+            // Dim the players that are not being controlled.
+            let shade = if i == self.active_player { 255 } else { 150 };
+            tex_player.set_color_mod(shade, shade, shade);
+            player.draw(tex_player, &mut self.camera, bgfx);
+        }
+        // Torches light the level around them, and in dark levels that is all
+        // the player has to go by.
+        let dark = self.metadata.contains_key("dark");
+        if settings::get().lighting && (dark || !self.flames.is_empty()) {
+            let mut lights: Vec<Light> = self.flames.iter().map(Fire::get_light).collect();
+            if dark {
+                for player in self.players.iter() {
+                    let center = player.get_center();
+                    lights.push(Light { x: center.x, y: center.y, radius: lighting::PLAYER_RADIUS, casts_shadows: false });
+                }
+            }
+            let darkness = if dark { lighting::DARK } else { lighting::DIM };
+            draw_lighting(&self.tiles, &lights, darkness, &self.camera, bgfx);
+        }
         for fire in self.flames.iter_mut() {
             fire.draw(context, &mut self.camera, bgfx)
         }
+        if !self.won && !self.failed {
+            self.hud.draw(context, bgfx, self);
+        }
+
+        if self.failed {
+            bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, 150));
+            bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+            bgfx.sdl.draw_rect(0, 0, self.camera.width as u32, self.camera.height as u32, FillType::FILL, false);
+
+            let font = context.load_font(*settings::FONT_DETAILS);
+
+            bgfx.sdl.set_draw_color(Color::from_rgba(255, 255, 255, 255));
+
+            // Render the title.
+            bgfx.sdl.draw_text("Failed!", font, 
+                self.camera.width as f32 / 2.0 - 4.0, 30.0, 4.0, 4.0, true, false);
+
+            // Render which limit ran out.
+            let reason = if self.get_time_left() == Some(0) { "Out of time" } else { "Out of pushes" };
+            bgfx.sdl.draw_text(reason, font, 
+                self.camera.width as f32 / 2.0, self.camera.height as f32 / 2.0, 3.0, 3.0, true, true);
+
+            bgfx.sdl.draw_text(&format!("Press {} to retry or {} to go back", describe(Action::Restart), describe(Action::Confirm)),
+                font, 
+                0.0, self.camera.height as f32 * 0.9, 2.0, 2.0, false, false);
+        }
+
+        if self.paused {
+            bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, 150));
+            bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+            bgfx.sdl.draw_rect(0, 0, self.camera.width as u32, self.camera.height as u32, FillType::FILL, false);
+            match self.options.as_ref() {
+                Some(options) => options.draw(context, bgfx, &self.camera),
+                None => self.draw_pause_menu(context, bgfx),
+            }
+        }
+
         if self.won {
             bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, 150));
             bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
@@ -171,12 +426,14 @@ impl State<BarnContext> for GameState {
             bgfx.sdl.draw_text(&format!("Time: {} seconds", self.time_str), font, 
                 self.camera.width as f32 / 2.0, self.camera.height as f32 / 2.0, 3.0, 3.0, true, true);
 
-            // Render number of moves.
-            bgfx.sdl.draw_text(&format!("Moves taken: {}", self.moves), font, 
+            // Render number of steps and pushes.
+            bgfx.sdl.draw_text(&format!("Steps taken: {}", self.steps), font, 
                 self.camera.width as f32 / 2.0, self.camera.height as f32 * 0.6, 3.0, 3.0, true, true);
+            bgfx.sdl.draw_text(&format!("Blocks pushed: {}", self.pushes), font, 
+                self.camera.width as f32 / 2.0, self.camera.height as f32 * 0.7, 3.0, 3.0, true, true);
 
             // Render number of moves.
-            bgfx.sdl.draw_text("Press enter to go back", font, 
+            bgfx.sdl.draw_text(&format!("Press {} to go back", describe(Action::Confirm)), font, 
                 0.0, self.camera.height as f32 * 0.9, 2.0, 2.0, false, false);
             
         }
@@ -190,11 +447,15 @@ impl State<BarnContext> for GameState {
         self.camera.width = 800;
         self.camera.height = 600;
         self.load_level(String::from(&self.level_path), context);
-        self.time = Instant::now();
+        self.play_level_audio(context);
     }
 
     fn on_exit(&mut self, context: &mut BarnContext) {
-        self.flames.clear();
+        for mut fire in self.flames.drain(..) {
+            fire.stop_sound();
+        }
+        audio::fade_out_world_sounds();
+        audio::play_ambience(context, None);
     }
 
     fn get_name(&mut self) -> String {
@@ -206,16 +467,32 @@ impl GameState {
     pub fn new(path: String) -> Self {
         GameState {
             level_path: path,
+            title: String::new(),
+            metadata: HashMap::new(),
             won: false,
-            time: Instant::now(),
+            failed: false,
+            paused: false,
+            confirming: false,
+            pause_option: 0,
+            options: None,
+            elapsed: 0.0,
             time_str: String::from(""),
-            moves: 0,
+            steps: 0,
+            pushes: 0,
+            move_limit: None,
+            time_limit: None,
             flames: Vec::new(),
             tiles: Vec::new(),
             blocks: Vec::new(),
             eyes: Vec::new(),
-            player: Player::new(),
-            camera: Camera::new()
+            watchers: Vec::new(),
+            players: Vec::new(),
+            active_player: 0,
+            step_queue: VecDeque::new(),
+            held_step: None,
+            repeat_timer: 0.0,
+            camera: Camera::new(),
+            hud: Hud::new(),
         }
     }
 
@@ -227,26 +504,45 @@ impl GameState {
         let mut temp_eyes: Vec<Eye> = Vec::new();
         self.tiles.clear();
         self.eyes.clear();
-        self.player = Player::new();
+        self.watchers.clear();
+        for mut fire in self.flames.drain(..) {
+            fire.stop_sound();
+        }
+        self.metadata.clear();
+        self.players.clear();
+        self.active_player = 0;
+        self.step_queue.clear();
+        self.held_step = None;
+        self.won = false;
+        self.failed = false;
+        self.steps = 0;
+        self.pushes = 0;
+        self.paused = false;
+        self.confirming = false;
+        self.elapsed = 0.0;
+        self.time_str = String::from("");
         let mut skip = true;
         for line in f.lines() {
             if skip {
-                // Skip the first line of the file.
+                // The first line of the file is the level title.
+                self.title = line.to_string();
                 skip = false;
                 continue;
             }
+            if line.starts_with('@') {
+                // Metadata lines look like `@key value`; the value may be left out for flags.
+                let mut parts = line[1..].splitn(2, ' ');
+                let key = parts.next().unwrap_or("").trim().to_string();
+                let value = parts.next().unwrap_or("").trim().to_string();
+                self.metadata.insert(key, value);
+                continue;
+            }
             let mut curx: i32 = 10;
-            for c in line.chars() {
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                let (x, y) = (curx as f32, cury as f32);
                 if TILE_CHARS.contains(&c) {
-                    self.tiles.push(Tile {
-                        texture: GameState::get_texture_name(c),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
-                        iswall: false,
-                        isblock: false,
-                        moving: false
-                    });
+                    self.tiles.push(Tile::new(&GameState::get_texture_name(c), x, y, TILE_WIDTH, TILE_HEIGHT));
                     curx += TILE_WIDTH as i32;
                 } else if c == 'f' {
                     let mut flame = Fire::new();
@@ -254,56 +550,104 @@ impl GameState {
                     flame.y = cury;
                     self.flames.push(flame);
                     self.tiles.push(Tile {
-                        texture: String::from("res/img/torch.png"),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
                         iswall: true,
-                        isblock: false,
-                        moving: false, 
+                        ..Tile::new("res/img/torch.png", x, y, TILE_WIDTH, TILE_HEIGHT)
                     });
                     curx += TILE_WIDTH as i32;
                 } else if c == 'x' {
                     self.tiles.push(Tile {
-                        texture: String::from("res/img/grayblock.png"),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
                         iswall: true,
-                        isblock: false,
-                        moving: false, 
+                        ..Tile::new("res/img/grayblock.png", x, y, TILE_WIDTH, TILE_HEIGHT)
                     });
                     curx += TILE_WIDTH as i32;
-                } else if c == 'b' {
-                    temp_blocks.push(Tile {
-                        texture: String::from("res/img/blueblock.png"),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
-                        iswall: false,
-                        isblock: true,
-                        moving: false, 
+                } else if c == 't' {
+                    self.tiles.push(Tile {
+                        isteleporter: true,
+                        channel: GameState::read_channel(&mut chars),
+                        ..Tile::new("res/img/teleporter.png", x, y, TILE_WIDTH, TILE_HEIGHT)
                     });
-                } else if c == 'g' {
-                    temp_blocks.push(Tile {
-                        texture: String::from("res/img/greenblock.png"),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
-                        iswall: false,
-                        isblock: true,
-                        moving: false, 
+                    curx += TILE_WIDTH as i32;
+                } else if c == '_' {
+                    self.tiles.push(Tile {
+                        isplate: true,
+                        channel: GameState::read_channel(&mut chars),
+                        ..Tile::new("res/img/plate.png", x, y, TILE_WIDTH, TILE_HEIGHT)
                     });
-                } else if c == 'r' {
-                    temp_blocks.push(Tile {
-                        texture: String::from("res/img/redblock.png"),
-                        bb: BoundingBox2D {origin: Vector2 {x: curx as f32, y: cury as f32}, width: TILE_WIDTH, height: TILE_HEIGHT},
-                        target_pos: Vector2 {x: curx as f32, y: cury as f32},
-                        resistance: 30.0,
-                        iswall: false,
-                        isblock: true,
-                        moving: false, 
+                    curx += TILE_WIDTH as i32;
+                } else if c == 's' {
+                    self.tiles.push(Tile {
+                        isswitch: true,
+                        channel: GameState::read_channel(&mut chars),
+                        ..Tile::new("res/img/switch.png", x, y, TILE_WIDTH, TILE_HEIGHT)
+                    });
+                    curx += TILE_WIDTH as i32;
+                } else if c == '#' {
+                    self.tiles.push(Tile {
+                        iswall: true,
+                        isgate: true,
+                        channel: GameState::read_channel(&mut chars),
+                        ..Tile::new("res/img/gate.png", x, y, TILE_WIDTH, TILE_HEIGHT)
                     });
+                    curx += TILE_WIDTH as i32;
+                } else if c == 'd' {
+                    let color = match chars.next_if(|c| ['b', 'g', 'r'].contains(c)) {
+                        Some('g') => "green",
+                        Some('r') => "red",
+                        _ => "blue",
+                    };
+                    self.tiles.push(Tile {
+                        isdoor: true,
+                        color: String::from(color),
+                        ..Tile::new(&format!("res/img/{}door.png", color), x, y, TILE_WIDTH, TILE_HEIGHT)
+                    });
+                    curx += TILE_WIDTH as i32;
+                } else if c == 'o' {
+                    self.tiles.push(Tile {
+                        ispit: true,
+                        ..Tile::new("res/img/pit.png", x, y, TILE_WIDTH, TILE_HEIGHT)
+                    });
+                    curx += TILE_WIDTH as i32;
+                } else if ARROW_CHARS.contains(&c) {
+                    let direction = match c {
+                        '<' => "left",
+                        '>' => "right",
+                        '^' => "up",
+                        _ => "down",
+                    };
+                    self.tiles.push(Tile {
+                        isarrow: true,
+                        direction: String::from(direction),
+                        ..Tile::new(&format!("res/img/arrow_{}.png", direction), x, y, TILE_WIDTH, TILE_HEIGHT)
+                    });
+                    curx += TILE_WIDTH as i32;
+                } else if c == '/' || c == '\\' {
+                    let mut mirror = Tile {
+                        iswall: true,
+                        ismirror: true,
+                        direction: c.to_string(),
+                        rotatable: chars.next_if_eq(&'\'').is_some(),
+                        ..Tile::new("res/img/mirror.png", x, y, TILE_WIDTH, TILE_HEIGHT)
+                    };
+                    mirror.frame = mirror.mirror_frame();
+                    self.tiles.push(mirror);
+                    curx += TILE_WIDTH as i32;
+                } else if BLOCK_CHARS.contains(&c) {
+                    let color = match c {
+                        'g' => "green",
+                        'r' => "red",
+                        _ => "blue",
+                    };
+                    let mut block = Tile {
+                        isblock: true,
+                        color: String::from(color),
+                        ..Tile::new(&format!("res/img/{}block.png", color), x, y, TILE_WIDTH, TILE_HEIGHT)
+                    };
+                    // An optional digit after the block sets its weight.
+                    if let Some(weight) = chars.next_if(|c| WEIGHT_CHARS.contains(c)) {
+                        block.weight = weight.to_digit(10).unwrap();
+                        block.reset_resistance();
+                    }
+                    temp_blocks.push(block);
                 } else if c == 'B' {
                     temp_eyes.push(Eye {
                         direction: String::from("left"),
@@ -343,10 +687,18 @@ impl GameState {
                         deltay: 0.0,
                         anger: 0,
                     });
-                } else if c == 'p' {
-                    self.player.pos.x = (curx + (TILE_WIDTH / 2) as i32 - (self.player.width / 2) as i32) as f32;
-                    self.player.pos.y =
-                        (cury + 3 - (TILE_HEIGHT / 2) as i32 + (self.player.height / 2) as i32) as f32;
+                } else if c == 'w' {
+                    let direction = match chars.next_if(|c| ARROW_CHARS.contains(c)) {
+                        Some('<') => "left",
+                        Some('^') => "up",
+                        Some('v') => "down",
+                        _ => "right",
+                    };
+                    self.watchers.push(Watcher::new(x, y, TILE_WIDTH, TILE_HEIGHT, direction));
+                } else if c == 'p' || c == 'q' {
+                    let mut player = Player::new();
+                    player.place_on_tile(Vector2 {x: x, y: y}, TILE_WIDTH, TILE_HEIGHT);
+                    self.players.push(player);
                 } else if c == ' ' {
                     curx += TILE_WIDTH as i32;
                 }
@@ -360,10 +712,190 @@ impl GameState {
             self.eyes.push(eye);
         }
 
-        self.camera.minx = self.player.pos.x as i32 + self.player.width as i32 / 2;
-        self.camera.maxx = self.player.pos.x as i32+ self.player.width as i32 / 2;
-        self.camera.miny = self.player.pos.y as i32 - self.player.height as i32 / 2;
-        self.camera.maxy = self.player.pos.y as i32 - self.player.height as i32 / 2;
+        if self.players.is_empty() {
+            self.players.push(Player::new());
+        }
+
+        self.move_limit = self.read_limit("max_moves", "par_moves");
+        self.time_limit = self.read_limit("max_time", "par_time");
+
+        // Keep the camera within the level.
+        let left = self.tiles.iter().map(|tile| tile.bb.origin.x as i32).min().unwrap_or(0);
+        let top = self.tiles.iter().map(|tile| tile.bb.origin.y as i32).min().unwrap_or(0);
+        let right = self.tiles.iter().map(|tile| (tile.bb.origin.x + tile.bb.width as f32) as i32).max().unwrap_or(0);
+        let bottom = self.tiles.iter().map(|tile| (tile.bb.origin.y + tile.bb.height as f32) as i32).max().unwrap_or(0);
+        self.camera.set_bounds(left, top, right, bottom);
+        let center = self.players[self.active_player].get_center();
+        self.camera.panning = false;
+        self.camera.follow(center.x, center.y, 0.0);
+    }
+
+    // Levels can pick their music and background loop with `@music` and
+    // `@ambience`, naming files in res/sound.
+    fn play_level_audio(&self, context: &mut BarnContext) {
+        let music = self.get_sound_path("music").unwrap_or_else(|| String::from(audio::MENU_MUSIC));
+        let ambience = self.get_sound_path("ambience");
+        audio::play_music(context, &music);
+        audio::play_ambience(context, ambience.as_deref());
+    }
+
+    fn get_sound_path(&self, key: &str) -> Option<String> {
+        let path = format!("res/sound/{}", self.metadata.get(key)?);
+        if Path::new(&path).exists() {
+            Some(path)
+        } else {
+            log::warn!("Could not find {}", path);
+            None
+        }
+    }
+
+    // A level's hard limit always applies; its par only counts in challenge mode.
+    fn read_limit(&self, max_key: &str, par_key: &str) -> Option<u64> {
+        let max = self.metadata.get(max_key).and_then(|value| value.parse::<u64>().ok());
+        let par = if settings::get().challenge {
+            self.metadata.get(par_key).and_then(|value| value.parse::<u64>().ok())
+        } else {
+            None
+        };
+        match (max, par) {
+            (Some(max), Some(par)) => Some(max.min(par)),
+            (max, par) => max.or(par),
+        }
+    }
+
+    pub fn get_moves_left(&self) -> Option<u64> {
+        self.move_limit.map(|limit| limit.saturating_sub(self.pushes as u64))
+    }
+
+    pub fn get_time_left(&self) -> Option<u64> {
+        self.time_limit.map(|limit| limit.saturating_sub(self.elapsed as u64))
+    }
+
+    // Navigate the pause menu. Anything that throws away the attempt asks first.
+    fn update_pause_menu(&mut self, context: &mut BarnContext) -> Option<Box<dyn State<BarnContext>>> {
+        if let Some(options) = self.options.as_mut() {
+            if options.update(context) {
+                self.options = None;
+                self.hud.visible = settings::get().show_hud;
+                // Go back to the level's music if the music test changed it.
+                self.play_level_audio(context);
+            }
+            return None;
+        }
+        if self.confirming {
+            if is_just_pressed(&context.input, Action::Confirm) {
+                self.confirming = false;
+                audio::play_sound(context, "res/sound/enter.ogg");
+                match PAUSE_OPTIONS[self.pause_option] {
+                    "Restart" => self.load_level(String::from(&self.level_path), context),
+                    "Quit to Level Select" => return Some(Box::new(LevelSelectState::new(0))),
+                    "Quit to Menu" => return Some(Box::new(StartMenuState::new(0))),
+                    _ => {}
+                }
+            } else if is_just_pressed(&context.input, Action::Back) {
+                self.confirming = false;
+                audio::play_sound(context, "res/sound/back.ogg");
+            }
+            return None;
+        }
+
+        let prev_option = self.pause_option;
+        if is_just_pressed(&context.input, Action::Back) {
+            self.paused = false;
+        } else if is_just_pressed(&context.input, Action::MoveDown) {
+            self.pause_option = (self.pause_option + 1) % PAUSE_OPTIONS.len();
+        } else if is_just_pressed(&context.input, Action::MoveUp) {
+            self.pause_option = (self.pause_option + PAUSE_OPTIONS.len() - 1) % PAUSE_OPTIONS.len();
+        } else if is_just_pressed(&context.input, Action::Confirm) {
+            match PAUSE_OPTIONS[self.pause_option] {
+                "Resume" => self.paused = false,
+                "Options" => self.options = Some(OptionsMenu::new()),
+                _ => self.confirming = true,
+            }
+        }
+        if prev_option != self.pause_option {
+            audio::play_sound(context, "res/sound/select.ogg");
+        }
+        None
+    }
+
+    fn draw_pause_menu(&mut self, context: &mut BarnContext, bgfx: &mut BarnGFX) {
+        let font = context.load_font(*settings::FONT_DETAILS);
+        bgfx.sdl.set_draw_color(Color::WHITE);
+
+        // Render the title.
+        bgfx.sdl.draw_text("Paused", font, 
+            self.camera.width as f32 / 2.0, 30.0, 4.0, 4.0, true, false);
+
+        if self.confirming {
+            bgfx.sdl.draw_text(&format!("{}?", PAUSE_OPTIONS[self.pause_option]), font, 
+                self.camera.width as f32 / 2.0, self.camera.height as f32 / 2.0 - 40.0, 3.0, 3.0, true, true);
+            let prompt = format!("Progress will be lost. {} to confirm, {} to cancel",
+                describe(Action::Confirm), describe(Action::Back));
+            bgfx.sdl.draw_text(&prompt, font, 
+                self.camera.width as f32 / 2.0, self.camera.height as f32 / 2.0 + 20.0, 1.5, 1.5, true, true);
+            return;
+        }
+
+        // Render the options, highlighting the selected one.
+        for (i, option) in PAUSE_OPTIONS.iter().enumerate() {
+            let y = 200 + 70 * i as i32;
+            if i == self.pause_option {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+                bgfx.sdl.draw_rect(self.camera.width / 2 - 200, y, 400, 50, FillType::FILL, false);
+                bgfx.sdl.set_draw_color(Color::BLACK);
+            } else {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+                bgfx.sdl.draw_rect(self.camera.width / 2 - 200, y, 400, 50, FillType::LINE, false);
+            }
+            bgfx.sdl.draw_text(option, font, 
+                self.camera.width as f32 / 2.0, y as f32 + 25.0, 2.0, 2.0, true, true);
+        }
+    }
+
+    // Turn the rotatable mirror directly in front of the player.
+    fn rotate_mirror(&mut self) {
+        let target = self.players[self.active_player].get_reach();
+        for tile in self.tiles.iter_mut() {
+            if tile.ismirror && tile.rotatable && contains_point(tile, target) {
+                tile.rotate_mirror();
+            }
+        }
+    }
+
+    // Blocks that come to rest over a pit fall in and fill it, turning it into floor.
+    fn update_pits(&mut self, dt: f32) {
+        for i in 0..self.tiles.len() {
+            if !self.tiles[i].isblock || self.tiles[i].moving || self.tiles[i].target_pos != self.tiles[i].bb.origin {
+                continue;
+            }
+            let origin = self.tiles[i].bb.origin;
+            if self.tiles[i].falling > 0.0 {
+                self.tiles[i].falling += dt;
+            } else if self.tiles.iter().any(|tile| tile.ispit && tile.bb.origin == origin) {
+                self.tiles[i].falling = dt;
+            }
+            if self.tiles[i].falling >= FALL_TIME {
+                for tile in self.tiles.iter_mut() {
+                    if tile.ispit && tile.bb.origin == origin {
+                        tile.ispit = false;
+                        tile.texture = String::from("res/img/filled_pit.png");
+                    }
+                }
+            }
+        }
+        self.tiles.retain(|tile| tile.falling < FALL_TIME);
+    }
+
+    // Read the optional link id that follows a tile character, e.g. the `2` in `t2`.
+    fn read_channel(chars: &mut Peekable<Chars>) -> u32 {
+        match chars.peek().and_then(|c| c.to_digit(10)) {
+            Some(channel) => {
+                chars.next();
+                channel
+            }
+            None => 0,
+        }
     }
 
     fn get_texture_name(tile: char) -> String {
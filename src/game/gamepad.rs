@@ -0,0 +1,132 @@
+use sdl2::sys;
+use sdl2::sys::SDL_GameControllerAxis as Axis;
+use sdl2::sys::SDL_GameControllerButton as Button;
+
+use std::cell::RefCell;
+
+// Stick travel ignored before it counts as a direction, out of 32767.
+const DEADZONE: i16 = 12000;
+
+// Controller inputs the game reads, with the sticks folded into directions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PadInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    Start,
+    Back,
+    LeftShoulder,
+    RightShoulder,
+}
+
+const BUTTONS: [(Button, PadInput); 12] = [
+    (Button::SDL_CONTROLLER_BUTTON_DPAD_UP, PadInput::Up),
+    (Button::SDL_CONTROLLER_BUTTON_DPAD_DOWN, PadInput::Down),
+    (Button::SDL_CONTROLLER_BUTTON_DPAD_LEFT, PadInput::Left),
+    (Button::SDL_CONTROLLER_BUTTON_DPAD_RIGHT, PadInput::Right),
+    (Button::SDL_CONTROLLER_BUTTON_A, PadInput::A),
+    (Button::SDL_CONTROLLER_BUTTON_B, PadInput::B),
+    (Button::SDL_CONTROLLER_BUTTON_X, PadInput::X),
+    (Button::SDL_CONTROLLER_BUTTON_Y, PadInput::Y),
+    (Button::SDL_CONTROLLER_BUTTON_START, PadInput::Start),
+    (Button::SDL_CONTROLLER_BUTTON_BACK, PadInput::Back),
+    (Button::SDL_CONTROLLER_BUTTON_LEFTSHOULDER, PadInput::LeftShoulder),
+    (Button::SDL_CONTROLLER_BUTTON_RIGHTSHOULDER, PadInput::RightShoulder),
+];
+
+struct Gamepads {
+    initialised: bool,
+    controllers: Vec<*mut sys::SDL_GameController>,
+    held: Vec<PadInput>,
+    prev_held: Vec<PadInput>,
+}
+
+thread_local! {
+    static GAMEPADS: RefCell<Gamepads> = RefCell::new(Gamepads {
+        initialised: false,
+        controllers: Vec::new(),
+        held: Vec::new(),
+        prev_held: Vec::new(),
+    });
+}
+
+// Poll every connected controller. Called once at the start of each state update;
+// controllers plugged in or pulled out while the game runs are picked up here.
+pub fn update() {
+    GAMEPADS.with(|pads| {
+        let mut pads = pads.borrow_mut();
+        unsafe {
+            if !pads.initialised {
+                pads.initialised = true;
+                if sys::SDL_InitSubSystem(sys::SDL_INIT_GAMECONTROLLER) != 0 {
+                    log::warn!("Could not initialise game controllers");
+                }
+            }
+            sys::SDL_GameControllerUpdate();
+
+            // Drop controllers that were unplugged.
+            pads.controllers.retain(|&controller| {
+                let attached = sys::SDL_GameControllerGetAttached(controller) == sys::SDL_bool::SDL_TRUE;
+                if !attached {
+                    sys::SDL_GameControllerClose(controller);
+                }
+                attached
+            });
+
+            // Open any newly connected ones.
+            for index in 0..sys::SDL_NumJoysticks() {
+                if sys::SDL_IsGameController(index) != sys::SDL_bool::SDL_TRUE {
+                    continue;
+                }
+                let id = sys::SDL_JoystickGetDeviceInstanceID(index);
+                let is_open = pads.controllers.iter().any(|&controller| {
+                    sys::SDL_JoystickInstanceID(sys::SDL_GameControllerGetJoystick(controller)) == id
+                });
+                if !is_open {
+                    let controller = sys::SDL_GameControllerOpen(index);
+                    if !controller.is_null() {
+                        pads.controllers.push(controller);
+                    }
+                }
+            }
+
+            let mut held = Vec::new();
+            for &controller in pads.controllers.iter() {
+                for (button, input) in BUTTONS.iter() {
+                    if sys::SDL_GameControllerGetButton(controller, *button) != 0 {
+                        held.push(*input);
+                    }
+                }
+                let x = sys::SDL_GameControllerGetAxis(controller, Axis::SDL_CONTROLLER_AXIS_LEFTX);
+                let y = sys::SDL_GameControllerGetAxis(controller, Axis::SDL_CONTROLLER_AXIS_LEFTY);
+                if x < -DEADZONE {
+                    held.push(PadInput::Left);
+                } else if x > DEADZONE {
+                    held.push(PadInput::Right);
+                }
+                if y < -DEADZONE {
+                    held.push(PadInput::Up);
+                } else if y > DEADZONE {
+                    held.push(PadInput::Down);
+                }
+            }
+            pads.prev_held = std::mem::replace(&mut pads.held, held);
+        }
+    });
+}
+
+pub fn is_held(input: PadInput) -> bool {
+    GAMEPADS.with(|pads| pads.borrow().held.contains(&input))
+}
+
+pub fn is_just_held(input: PadInput) -> bool {
+    GAMEPADS.with(|pads| {
+        let pads = pads.borrow();
+        pads.held.contains(&input) && !pads.prev_held.contains(&input)
+    })
+}
@@ -4,26 +4,37 @@ use barn::game::barn_context::BarnContext;
 use barn::game::context::Context;
 use barn::game::state::State;
 use barn::graphics::color::Color;
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::controls::{self, describe, is_just_pressed, Action};
 use crate::game::start_menu_state::StartMenuState;
 use crate::settings;
 
-use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 
+const CONTROLS: [Action; 10] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::SwitchPlayer,
+    Action::Grab,
+    Action::Rotate,
+    Action::ToggleHud,
+    Action::Restart,
+    Action::Pause,
+];
+
 pub struct HelpState {
     pub camera: Camera,
 }
 
 impl State<BarnContext> for HelpState {
     fn update(&mut self, context: &mut BarnContext, dt: f32) -> Option<Box<dyn State<BarnContext>>> {
-        if context.input.key_just_pressed(&Keycode::Return) {
-            if settings::ENABLE_SOUND {
-                let back_fx = context.load_sound(String::from("res/sound/back.ogg"));
-                let channel = sdl2::mixer::Channel(2);
-                channel.play(back_fx, 0).unwrap();
-            }
+        controls::update();
+        if is_just_pressed(&context.input, Action::Confirm) || is_just_pressed(&context.input, Action::Back) {
+            audio::play_sound(context, "res/sound/back.ogg");
             
             return Some(Box::new(StartMenuState::new(1)));
         }
@@ -48,31 +59,21 @@ impl State<BarnContext> for HelpState {
             true,
             false);
 
-        bgfx.sdl.draw_text("Movement: Arrow Keys", font, 
-            self.camera.width as f32 / 2.0,
-            200.0,
-            3.0,
-            3.0,
-            true,
-            false);
-
-        bgfx.sdl.draw_text("Reset Puzzle: R", font, 
-            self.camera.width as f32 / 2.0,
-            300.0,
-            3.0,
-            3.0,
-            true,
-            false);
-
-        bgfx.sdl.draw_text("Exit Puzzle: Q", font, 
-            self.camera.width as f32 / 2.0,
-            400.0,
-            3.0,
-            3.0,
-            true,
-            false);
+        // List each action with the keys currently bound to it.
+        let mut y = 110.0;
+        for action in CONTROLS.iter() {
+            let line = format!("{}: {}", action.get_label(), describe(*action));
+            bgfx.sdl.draw_text(&line, font, 
+                self.camera.width as f32 / 2.0,
+                y,
+                2.0,
+                2.0,
+                true,
+                false);
+            y += 43.0;
+        }
 
-        bgfx.sdl.draw_text("Press enter to return to the menu.", font, 
+        bgfx.sdl.draw_text(&format!("Press {} to return to the menu.", describe(Action::Confirm)), font, 
             0.0,
             550.0,
             2.0,
@@ -0,0 +1,109 @@
+use barn::graphics::barn_gfx::BarnGFX;
+use barn::graphics::SdlBlendType;
+use barn::game::barn_context::BarnContext;
+use barn::graphics::color::Color;
+use barn::graphics::fill_type::FillType;
+use crate::game::game_state::GameState;
+use crate::settings;
+
+use sdl2::rect::Rect;
+
+const HUD_HEIGHT: u32 = 90;
+const ICON_SIZE: u32 = 32;
+
+// Play statistics drawn in screen space over the level.
+pub struct Hud {
+    pub visible: bool,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud {
+            visible: settings::get().show_hud,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn draw(&self, context: &mut BarnContext, bgfx: &mut BarnGFX, game: &GameState) {
+        if !self.visible {
+            self.draw_limits(context, bgfx, game);
+            return;
+        }
+        let width = game.camera.width;
+
+        bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, 150));
+        bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+        bgfx.sdl.draw_rect(0, 0, width as u32, HUD_HEIGHT, FillType::FILL, false);
+
+        let font = context.load_font(*settings::FONT_DETAILS);
+        bgfx.sdl.set_draw_color(Color::WHITE);
+
+        // Render the level title.
+        bgfx.sdl.draw_text(&game.title, font, width as f32 / 2.0, 5.0, 2.0, 2.0, true, false);
+
+        // Render the stats, counting down any limits the level sets.
+        let time = match game.get_time_left() {
+            Some(time_left) => format!("Time left: {}", time_left),
+            None => format!("Time: {}", game.elapsed as u64),
+        };
+        let pushes = match game.get_moves_left() {
+            Some(moves_left) => format!("Pushes: {} ({} left)", game.pushes, moves_left),
+            None => format!("Pushes: {}", game.pushes),
+        };
+        bgfx.sdl.draw_text(&format!("{}  Steps: {}  {}", time, game.steps, pushes), font,
+            10.0, 45.0, 2.0, 2.0, false, false);
+
+        // Render an icon per eye, lit up once it can see its block.
+        let socket = context.load_texture(String::from("res/img/socket.png"));
+        for (i, eye) in game.eyes.iter().enumerate() {
+            let x = width - 10 - (game.eyes.len() - i) as i32 * (ICON_SIZE as i32 + 6);
+            let dest = Rect::new(x, 50, ICON_SIZE, ICON_SIZE);
+            if !eye.solved {
+                socket.set_color_mod(90, 90, 90);
+            } else if eye.color == "red" {
+                socket.set_color_mod(255, 0, 0);
+            } else if eye.color == "green" {
+                socket.set_color_mod(0, 255, 0);
+            } else {
+                socket.set_color_mod(0, 0, 255);
+            }
+            bgfx.sdl.draw_texture(socket, None, Some(dest));
+        }
+        socket.set_color_mod(255, 255, 255);
+        for (i, eye) in game.eyes.iter().enumerate() {
+            if eye.solved {
+                let x = width - 10 - (game.eyes.len() - i) as i32 * (ICON_SIZE as i32 + 6);
+                let pupil = context.load_texture(format!("res/img/{}pupil.png", eye.color));
+                bgfx.sdl.draw_texture(pupil, None, Some(Rect::new(x, 50, ICON_SIZE, ICON_SIZE)));
+            }
+        }
+    }
+
+    // With the HUD hidden the level's limits stay on screen, since running out
+    // of either fails the level.
+    fn draw_limits(&self, context: &mut BarnContext, bgfx: &mut BarnGFX, game: &GameState) {
+        let mut lines = Vec::new();
+        if let Some(moves_left) = game.get_moves_left() {
+            lines.push(format!("Pushes left: {}", moves_left));
+        }
+        if let Some(time_left) = game.get_time_left() {
+            lines.push(format!("Time left: {}", time_left));
+        }
+        if lines.is_empty() {
+            return;
+        }
+
+        bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, 150));
+        bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+        bgfx.sdl.draw_rect(0, 0, 240, 10 + 40 * lines.len() as u32, FillType::FILL, false);
+
+        let font = context.load_font(*settings::FONT_DETAILS);
+        bgfx.sdl.set_draw_color(Color::WHITE);
+        for (i, line) in lines.iter().enumerate() {
+            bgfx.sdl.draw_text(line, font, 10.0, 5.0 + 40.0 * i as f32, 2.0, 2.0, false, false);
+        }
+    }
+}
@@ -3,14 +3,16 @@ use barn::game::barn_context::BarnContext;
 use barn::graphics::color::Color;
 use barn::graphics::fill_type::FillType;
 use barn::game::state::State;
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::controls::{self, describe, is_just_pressed, Action};
 use crate::game::eye::Eye;
 use crate::game::game_state::GameState;
+use crate::game::mouse;
 use crate::game::start_menu_state::StartMenuState;
 use crate::game::tile::Tile;
 use crate::settings;
 
-use sdl2::keyboard::Keycode;
 use std::collections::HashMap;
 use std::fs;
 
@@ -25,45 +27,53 @@ pub struct LevelSelectState {
 
 impl State<BarnContext> for LevelSelectState {
     fn update(&mut self, context: &mut BarnContext, dt: f32) -> Option<Box<dyn State<BarnContext>>> {
+        controls::update();
         let prev_option = self.selected_option;
-        if context.input.key_just_pressed(&Keycode::Down) {
+        if is_just_pressed(&context.input, Action::MoveDown) {
             if self.selected_option == 4 {
                 self.selected_option = 0;
             } else {
                 self.selected_option += 1;
             }
-        } else if context.input.key_just_pressed(&Keycode::Up) {
+        } else if is_just_pressed(&context.input, Action::MoveUp) {
             if self.selected_option <= 0 {
                 self.selected_option = 4;
             } else {
                 self.selected_option -= 1;
             }
-        } else if context.input.key_just_pressed(&Keycode::Left) {
+        } else if is_just_pressed(&context.input, Action::MoveLeft) {
             self.selected_option = -1;
-        } else if context.input.key_just_pressed(&Keycode::Right) && self.selected_option == -1 {
+        } else if is_just_pressed(&context.input, Action::MoveRight) && self.selected_option == -1 {
             self.selected_option = 0;
-        } else if context.input.key_just_pressed(&Keycode::B) {
+        } else if is_just_pressed(&context.input, Action::Back) {
             return Some(Box::new(StartMenuState::new(0)));
+        } else if is_just_pressed(&context.input, Action::Challenge) {
+            settings::update(|settings| settings.challenge = !settings.challenge);
+            audio::play_sound(context, "res/sound/select.ogg");
         }
-        if prev_option != self.selected_option && settings::ENABLE_SOUND {
-            let select_fx = context.load_sound(String::from("res/sound/select.ogg"));
-            let channel = sdl2::mixer::Channel(1);
-            channel.play(select_fx, 0);
+
+        // Select the level or back button under the cursor.
+        if mouse::has_moved() || mouse::is_clicked() {
+            if mouse::is_over(0, self.camera.height - 50, 100, 50) {
+                self.selected_option = -1;
+            }
+            for i in 0..self.options.len() as i32 {
+                if mouse::is_over(self.camera.width / 2 - 250, 150 + 50 * i, 500, 50) {
+                    self.selected_option = i;
+                }
+            }
         }
-        if context.input.key_just_pressed(&Keycode::Return) {
+        if prev_option != self.selected_option {
+            audio::play_sound(context, "res/sound/select.ogg");
+        }
+        let clicked = mouse::is_clicked() && (mouse::is_over(0, self.camera.height - 50, 100, 50)
+            || (0..self.options.len() as i32).any(|i| mouse::is_over(self.camera.width / 2 - 250, 150 + 50 * i, 500, 50)));
+        if is_just_pressed(&context.input, Action::Confirm) || clicked {
             if self.selected_option == -1 {
-                if settings::ENABLE_SOUND {
-                    let back_fx = context.load_sound(String::from("res/sound/back.ogg"));
-                    let channel = sdl2::mixer::Channel(2);
-                    channel.play(back_fx, 0);
-                }
+                audio::play_sound(context, "res/sound/back.ogg");
                 return Some(Box::new(StartMenuState::new(0)));
             } else {
-                if settings::ENABLE_SOUND {
-                    let enter_fx = context.load_sound(String::from("res/sound/enter.ogg"));
-                    let channel = sdl2::mixer::Channel(2);
-                    channel.play(enter_fx, 0);
-                }
+                audio::play_sound(context, "res/sound/enter.ogg");
                 let key = self.options[self.selected_option as usize].clone();
                 let path = self.levels.get(&key).unwrap();
                 return Some(Box::new(GameState::new(path.to_string())));
@@ -157,6 +167,17 @@ impl State<BarnContext> for LevelSelectState {
             2.0,
             false,
             false);
+
+        // Render the challenge toggle.
+        let challenge = if settings::get().challenge { "On" } else { "Off" };
+        bgfx.sdl.set_draw_color(Color::WHITE);
+        bgfx.sdl.draw_text(&format!("Challenge ({}): {}", describe(Action::Challenge), challenge), font, 
+            (self.camera.width - 300) as f32,
+            (self.camera.height - 50) as f32,
+            2.0,
+            2.0,
+            false,
+            false);
         bgfx.sdl.present();
     }
 
@@ -181,6 +202,7 @@ impl State<BarnContext> for LevelSelectState {
             self.options.push(key.to_string());
         }
         self.options.sort();
+        audio::play_music(context, audio::MENU_MUSIC);
     }
 
     fn on_exit(&mut self, context: &mut BarnContext) {
@@ -0,0 +1,97 @@
+use barn::graphics::barn_gfx::BarnGFX;
+use barn::graphics::SdlBlendType;
+use barn::graphics::color::Color;
+use barn::graphics::fill_type::FillType;
+use barn::math::vector2::Vector2;
+use crate::game::camera::Camera;
+use crate::game::pathfinding::get_cell;
+use crate::game::tile::Tile;
+
+use std::collections::HashSet;
+
+// Size of the squares the light map is worked out on, in pixels.
+const CELL_SIZE: i32 = 16;
+// How far along a ray to look for walls between checks, in pixels.
+const RAY_STEP: f32 = 8.0;
+
+pub const TORCH_RADIUS: f32 = 260.0;
+pub const PLAYER_RADIUS: f32 = 90.0;
+// How dark unlit floor gets, out of 255, in normal and `@dark` levels.
+pub const DIM: u8 = 110;
+pub const DARK: u8 = 245;
+
+pub struct Light {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    // Whether walls block this light.
+    pub casts_shadows: bool,
+}
+
+// Darken everything on screen that the lights don't reach. Each light fades out
+// towards the edge of its radius and walls between it and a spot shade it.
+pub fn draw_lighting(tiles: &Vec<Tile>, lights: &Vec<Light>, darkness: u8, camera: &Camera, bgfx: &mut BarnGFX) {
+    let walls: HashSet<(i32, i32)> = tiles.iter()
+        .filter(|tile| tile.iswall)
+        .map(|tile| get_cell(tile.bb.origin))
+        .collect();
+    bgfx.sdl.set_blend_mode(SdlBlendType::Blend);
+    let left = camera.x - camera.x.rem_euclid(CELL_SIZE);
+    let top = camera.y - camera.y.rem_euclid(CELL_SIZE);
+    for y in (top..camera.y + camera.height).step_by(CELL_SIZE as usize) {
+        for x in (left..camera.x + camera.width).step_by(CELL_SIZE as usize) {
+            let point = Vector2 {
+                x: (x + CELL_SIZE / 2) as f32,
+                y: (y + CELL_SIZE / 2) as f32,
+            };
+            let light = get_light(&walls, lights, point);
+            let alpha = (darkness as f32 * (1.0 - light)) as u8;
+            if alpha == 0 {
+                continue;
+            }
+            bgfx.sdl.set_draw_color(Color::from_rgba(0, 0, 0, alpha));
+            bgfx.sdl.draw_rect(x - camera.x, y - camera.y, CELL_SIZE as u32, CELL_SIZE as u32, FillType::FILL, false);
+        }
+    }
+}
+
+// How lit a point is, from 0 to 1.
+fn get_light(walls: &HashSet<(i32, i32)>, lights: &Vec<Light>, point: Vector2) -> f32 {
+    let mut total: f32 = 0.0;
+    for light in lights.iter() {
+        let (dx, dy) = (point.x - light.x, point.y - light.y);
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance >= light.radius {
+            continue;
+        }
+        if light.casts_shadows && is_shadowed(walls, light, point, distance) {
+            continue;
+        }
+        let falloff = 1.0 - distance / light.radius;
+        total += falloff * falloff;
+        if total >= 1.0 {
+            return 1.0;
+        }
+    }
+    total
+}
+
+// Whether a wall stands between a light and a point. The walls the light and the
+// point are in don't count, so torches shine out of their walls and wall faces
+// still catch the light.
+fn is_shadowed(walls: &HashSet<(i32, i32)>, light: &Light, point: Vector2, distance: f32) -> bool {
+    let start = get_cell(Vector2 { x: light.x, y: light.y });
+    let end = get_cell(point);
+    let steps = (distance / RAY_STEP) as i32;
+    for i in 1..steps {
+        let t = i as f32 / steps as f32;
+        let cell = get_cell(Vector2 {
+            x: light.x + (point.x - light.x) * t,
+            y: light.y + (point.y - light.y) * t,
+        });
+        if cell != start && cell != end && walls.contains(&cell) {
+            return true;
+        }
+    }
+    false
+}
@@ -1,12 +1,23 @@
+pub mod audio;
 pub mod camera;
+pub mod controls;
+pub mod controls_menu;
 pub mod eye;
 pub mod fire;
+pub mod gamepad;
+pub mod hud;
+pub mod lighting;
+pub mod mouse;
+pub mod pathfinding;
 pub mod physics;
 pub mod player;
+pub mod signal;
 pub mod tile;
+pub mod watcher;
 
 pub mod credits_state;
 pub mod game_state;
 pub mod help_state;
 pub mod level_select_state;
+pub mod options_state;
 pub mod start_menu_state;
\ No newline at end of file
@@ -0,0 +1,83 @@
+use crate::settings;
+
+use sdl2::sys;
+
+use std::cell::RefCell;
+
+struct Mouse {
+    x: i32,
+    y: i32,
+    moved: bool,
+    down: bool,
+    prev_down: bool,
+}
+
+thread_local! {
+    static MOUSE: RefCell<Mouse> = RefCell::new(Mouse {
+        x: 0,
+        y: 0,
+        moved: false,
+        down: false,
+        prev_down: false,
+    });
+}
+
+// Read the cursor and left button. Called once at the start of each state update.
+pub fn update() {
+    MOUSE.with(|mouse| {
+        let mut mouse = mouse.borrow_mut();
+        let (mut x, mut y) = (0, 0);
+        let buttons = unsafe { sys::SDL_GetMouseState(&mut x, &mut y) };
+        let (x, y) = to_logical(x, y);
+        mouse.moved = x != mouse.x || y != mouse.y;
+        mouse.x = x;
+        mouse.y = y;
+        mouse.prev_down = mouse.down;
+        mouse.down = buttons & (1 << (sys::SDL_BUTTON_LEFT - 1)) != 0;
+    });
+}
+
+// Undo the window scaling so the cursor lines up with what was drawn.
+fn to_logical(x: i32, y: i32) -> (i32, i32) {
+    let window = match settings::get_window() {
+        Some(window) => window,
+        None => return (x, y),
+    };
+    unsafe {
+        let renderer = sys::SDL_GetRenderer(window);
+        if renderer.is_null() {
+            return (x, y);
+        }
+        let (mut scale_x, mut scale_y) = (1.0, 1.0);
+        sys::SDL_RenderGetScale(renderer, &mut scale_x, &mut scale_y);
+        let mut viewport = sys::SDL_Rect { x: 0, y: 0, w: 0, h: 0 };
+        sys::SDL_RenderGetViewport(renderer, &mut viewport);
+        ((x as f32 / scale_x) as i32 - viewport.x, (y as f32 / scale_y) as i32 - viewport.y)
+    }
+}
+
+pub fn get_position() -> (i32, i32) {
+    MOUSE.with(|mouse| {
+        let mouse = mouse.borrow();
+        (mouse.x, mouse.y)
+    })
+}
+
+// Whether the cursor moved this frame; menus only follow it when it does, so
+// that it doesn't fight the keyboard.
+pub fn has_moved() -> bool {
+    MOUSE.with(|mouse| mouse.borrow().moved)
+}
+
+pub fn is_clicked() -> bool {
+    MOUSE.with(|mouse| {
+        let mouse = mouse.borrow();
+        mouse.down && !mouse.prev_down
+    })
+}
+
+// Whether the cursor is inside the given screen rectangle.
+pub fn is_over(x: i32, y: i32, width: u32, height: u32) -> bool {
+    let (mouse_x, mouse_y) = get_position();
+    mouse_x >= x && mouse_x < x + width as i32 && mouse_y >= y && mouse_y < y + height as i32
+}
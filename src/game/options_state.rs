@@ -0,0 +1,204 @@
+use barn::graphics::barn_gfx::BarnGFX;
+use barn::game::barn_context::BarnContext;
+use barn::graphics::color::Color;
+use barn::graphics::fill_type::FillType;
+use barn::game::state::State;
+use crate::game::audio;
+use crate::game::camera::Camera;
+use crate::game::controls::{self, is_just_pressed, Action};
+use crate::game::controls_menu::ControlsMenu;
+use crate::game::start_menu_state::StartMenuState;
+use crate::settings;
+
+pub const OPTIONS: [&str; 15] = ["Master Volume", "Music Volume", "SFX Volume", "Mute", "Fullscreen", "Window Scale", "Movement",
+    "Repeat Delay", "Repeat Rate", "Gaze Beams", "HUD", "Lighting", "Music Test", "Controls", "Back"];
+
+// The list of settings, shared by the options screen and the pause menu.
+pub struct OptionsMenu {
+    pub selected_option: usize,
+    pub controls: Option<ControlsMenu>,
+    // The track picked in the music test.
+    pub track: usize,
+}
+
+impl OptionsMenu {
+    pub fn new() -> Self {
+        OptionsMenu {
+            selected_option: 0,
+            controls: None,
+            track: audio::get_music()
+                .and_then(|music| audio::TRACKS.iter().position(|(_, path)| *path == music))
+                .unwrap_or(0),
+        }
+    }
+
+    // Returns true once the player backs out of the menu.
+    pub fn update(&mut self, context: &mut BarnContext) -> bool {
+        if let Some(controls) = self.controls.as_mut() {
+            if controls.update(context) {
+                self.controls = None;
+            }
+            return false;
+        }
+
+        let prev_option = self.selected_option;
+        let mut step: i32 = 0;
+        if is_just_pressed(&context.input, Action::Back) {
+            return true;
+        } else if is_just_pressed(&context.input, Action::MoveDown) {
+            self.selected_option = (self.selected_option + 1) % OPTIONS.len();
+        } else if is_just_pressed(&context.input, Action::MoveUp) {
+            self.selected_option = (self.selected_option + OPTIONS.len() - 1) % OPTIONS.len();
+        } else if is_just_pressed(&context.input, Action::MoveLeft) {
+            step = -1;
+        } else if is_just_pressed(&context.input, Action::MoveRight) || is_just_pressed(&context.input, Action::Confirm) {
+            match OPTIONS[self.selected_option] {
+                "Back" => return true,
+                "Controls" => {
+                    self.controls = Some(ControlsMenu::new());
+                    return false;
+                }
+                _ => step = 1,
+            }
+        }
+
+        if step != 0 && OPTIONS[self.selected_option] == "Music Test" {
+            self.track = (self.track as i32 + step).rem_euclid(audio::TRACKS.len() as i32) as usize;
+            audio::play_music(context, audio::TRACKS[self.track].1);
+        } else if step != 0 {
+            let option = self.selected_option;
+            settings::update(|settings| match OPTIONS[option] {
+                "Master Volume" => settings.master_volume = step_volume(settings.master_volume, step),
+                "Music Volume" => settings.music_volume = step_volume(settings.music_volume, step),
+                "SFX Volume" => settings.sfx_volume = step_volume(settings.sfx_volume, step),
+                "Mute" => settings.mute = !settings.mute,
+                "Fullscreen" => settings.fullscreen = !settings.fullscreen,
+                "Window Scale" => settings.window_scale = (settings.window_scale as i32 + step).max(1).min(settings::MAX_WINDOW_SCALE as i32) as u32,
+                "Movement" => settings.grid_movement = !settings.grid_movement,
+                "Repeat Delay" => settings.repeat_delay = (settings.repeat_delay as i32 + step * 50)
+                    .max(settings::REPEAT_DELAY_RANGE.0 as i32).min(settings::REPEAT_DELAY_RANGE.1 as i32) as u32,
+                "Repeat Rate" => settings.repeat_interval = (settings.repeat_interval as i32 + step * 20)
+                    .max(settings::REPEAT_INTERVAL_RANGE.0 as i32).min(settings::REPEAT_INTERVAL_RANGE.1 as i32) as u32,
+                "Gaze Beams" => settings.show_gaze = !settings.show_gaze,
+                "HUD" => settings.show_hud = !settings.show_hud,
+                "Lighting" => settings.lighting = !settings.lighting,
+                _ => {}
+            });
+        }
+        if prev_option != self.selected_option || step != 0 {
+            audio::play_sound(context, "res/sound/select.ogg");
+        }
+        false
+    }
+
+    pub fn draw(&self, context: &mut BarnContext, bgfx: &mut BarnGFX, camera: &Camera) {
+        if let Some(controls) = self.controls.as_ref() {
+            controls.draw(context, bgfx, camera);
+            return;
+        }
+        let font = context.load_font(*settings::FONT_DETAILS);
+        let current = settings::get();
+        let on_off = |value: bool| String::from(if value { "On" } else { "Off" });
+
+        // Render screen title.
+        bgfx.sdl.set_draw_color(Color::WHITE);
+        bgfx.sdl.draw_text("Options", font,
+            camera.width as f32 / 2.0,
+            20.0,
+            3.0,
+            3.0,
+            true,
+            false);
+
+        for (i, option) in OPTIONS.iter().enumerate() {
+            let value = match *option {
+                "Master Volume" => format!("< {} >", current.master_volume),
+                "Music Volume" => format!("< {} >", current.music_volume),
+                "SFX Volume" => format!("< {} >", current.sfx_volume),
+                "Mute" => on_off(current.mute),
+                "Fullscreen" => on_off(current.fullscreen),
+                "Window Scale" => format!("< {}x >", current.window_scale),
+                "Movement" => String::from(if current.grid_movement { "Grid" } else { "Free" }),
+                "Repeat Delay" => format!("< {} ms >", current.repeat_delay),
+                "Repeat Rate" => format!("< {} ms >", current.repeat_interval),
+                "Gaze Beams" => on_off(current.show_gaze),
+                "HUD" => on_off(current.show_hud),
+                "Lighting" => on_off(current.lighting),
+                "Music Test" => format!("< {} >", audio::TRACKS[self.track].0),
+                _ => String::new(),
+            };
+            let y = 82 + 34 * i as i32;
+            if i == self.selected_option {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+                bgfx.sdl.draw_rect(camera.width / 2 - 250, y, 500, 30, FillType::FILL, false);
+                bgfx.sdl.set_draw_color(Color::BLACK);
+            } else {
+                bgfx.sdl.set_draw_color(Color::WHITE);
+            }
+            bgfx.sdl.draw_text(option, font,
+                (camera.width / 2 - 230) as f32,
+                y as f32 + 1.0,
+                1.5,
+                1.5,
+                false,
+                false);
+            bgfx.sdl.draw_text(&value, font,
+                (camera.width / 2 + 70) as f32,
+                y as f32 + 1.0,
+                1.5,
+                1.5,
+                false,
+                false);
+        }
+    }
+}
+
+fn step_volume(volume: u32, step: i32) -> u32 {
+    (volume as i32 + step).max(0).min(settings::MAX_VOLUME as i32) as u32
+}
+
+pub struct OptionsState {
+    pub camera: Camera,
+    pub menu: OptionsMenu,
+}
+
+impl State<BarnContext> for OptionsState {
+    fn update(&mut self, context: &mut BarnContext, _dt: f32) -> Option<Box<dyn State<BarnContext>>> {
+        controls::update();
+        if self.menu.update(context) {
+            audio::play_sound(context, "res/sound/back.ogg");
+            return Some(Box::new(StartMenuState::new(3)));
+        }
+        None
+    }
+
+    fn draw(&mut self, context: &mut BarnContext, bgfx: &mut BarnGFX) {
+        // Clear screen to black.
+        bgfx.sdl.set_draw_color(Color::BLACK);
+        bgfx.sdl.clear();
+
+        self.menu.draw(context, bgfx, &self.camera);
+
+        bgfx.sdl.present();
+    }
+
+    fn on_enter(&mut self, _context: &mut BarnContext) {
+        self.camera.width = 800;
+        self.camera.height = 600;
+    }
+
+    fn on_exit(&mut self, _context: &mut BarnContext) {}
+
+    fn get_name(&mut self) -> String {
+        String::from("options")
+    }
+}
+
+impl OptionsState {
+    pub fn new() -> Self {
+        OptionsState {
+            camera: Camera::new(),
+            menu: OptionsMenu::new(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use barn::math::vector2::Vector2;
+use crate::game::game_state::{TILE_HEIGHT, TILE_WIDTH};
+use crate::game::player::Player;
+use crate::game::tile::Tile;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Offset of the level grid from the top-left of the world.
+const GRID_ORIGIN: f32 = 10.0;
+
+const NEIGHBOURS: [(i32, i32, &str); 4] = [(-1, 0, "left"), (1, 0, "right"), (0, -1, "up"), (0, 1, "down")];
+
+// The grid cell containing a point.
+pub fn get_cell(point: Vector2) -> (i32, i32) {
+    (((point.x - GRID_ORIGIN) / TILE_WIDTH as f32).floor() as i32,
+        ((point.y - GRID_ORIGIN) / TILE_HEIGHT as f32).floor() as i32)
+}
+
+// Top-left corner of a grid cell.
+pub fn get_cell_origin(cell: (i32, i32)) -> Vector2 {
+    Vector2 {
+        x: GRID_ORIGIN + (cell.0 * TILE_WIDTH as i32) as f32,
+        y: GRID_ORIGIN + (cell.1 * TILE_HEIGHT as i32) as f32,
+    }
+}
+
+// Whether the player could stand in a cell: there has to be floor there and
+// nothing in the way, including the other players.
+fn is_walkable(tiles: &Vec<Tile>, others: &Vec<Player>, cell: (i32, i32)) -> bool {
+    let origin = get_cell_origin(cell);
+    tiles.iter().any(|tile| tile.bb.origin == origin)
+        && !tiles.iter().any(|tile| (tile.bb.origin == origin || tile.target_pos == origin) && tile.blocks_player())
+        && !others.iter().any(|other| get_cell(other.get_center()) == cell)
+}
+
+// Shortest walk between two cells that never pushes anything, as the cells to
+// visit after the start. Uses A* with the Manhattan distance.
+pub fn find_path(tiles: &Vec<Tile>, others: &Vec<Player>, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+    if !is_walkable(tiles, others, goal) {
+        return None;
+    }
+    let heuristic = |cell: (i32, i32)| (cell.0 - goal.0).abs() + (cell.1 - goal.1).abs();
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    let mut cost: HashMap<(i32, i32), i32> = HashMap::new();
+    open.push(Reverse((heuristic(start), start)));
+    cost.insert(start, 0);
+
+    while let Some(Reverse((_, cell))) = open.pop() {
+        if cell == goal {
+            let mut path = vec![cell];
+            let mut current = cell;
+            while let Some(prev) = came_from.get(&current) {
+                if *prev != start {
+                    path.push(*prev);
+                }
+                current = *prev;
+            }
+            path.reverse();
+            return Some(if goal == start { Vec::new() } else { path });
+        }
+        for (dx, dy, direction) in NEIGHBOURS.iter() {
+            let next = (cell.0 + dx, cell.1 + dy);
+            if !is_walkable(tiles, others, next) {
+                continue;
+            }
+            // Arrows can only be walked onto in the direction they point.
+            let origin = get_cell_origin(next);
+            if tiles.iter().any(|tile| tile.bb.origin == origin && tile.blocks_entry(direction)) {
+                continue;
+            }
+            let next_cost = cost[&cell] + 1;
+            if cost.get(&next).map_or(true, |&old| next_cost < old) {
+                cost.insert(next, next_cost);
+                came_from.insert(next, cell);
+                open.push(Reverse((next_cost + heuristic(next), next)));
+            }
+        }
+    }
+    None
+}
@@ -1,75 +1,375 @@
 use barn::math::vector2::Vector2;
+use crate::game::game_state::{TILE_HEIGHT, TILE_WIDTH};
+use crate::game::pathfinding::{get_cell, get_cell_origin};
 use crate::game::player::Player;
-use crate::game::tile::Tile;
+use crate::game::tile::{direction_of, Tile, BASE_RESISTANCE};
 
-use sdl2::mixer::Chunk;
+// Discrete moves reported back to the game so it can keep count of them.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MoveEvent {
+    Step,
+    Push,
+}
 
-pub fn handle_collisions(player: &mut Player, tiles: &mut Vec<Tile>, move_fx: &Chunk, dt: f32) {
-    handle_collision_x(player, tiles, move_fx, dt);
-    handle_collision_y(player, tiles, move_fx, dt);
+// `others` are the players not being controlled; they stand still and block movement.
+pub fn handle_collisions(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>, allow_chain: bool,
+    events: &mut Vec<MoveEvent>, dt: f32) {
+    handle_collision_x(player, others, tiles, allow_chain, events, dt);
+    handle_collision_y(player, others, tiles, allow_chain, events, dt);
 }
 
 pub fn does_intersect(player: &mut Player, tile: &mut Tile) -> bool {
-    (player.pos.x < (tile.bb.origin.x + tile.bb.width as f32) as f32)
-        && (player.pos.x + player.width as f32 > tile.bb.origin.x as f32)
-        && (player.pos.y + 25.0 < (tile.bb.origin.y + tile.bb.height as f32) as f32)
-        && (player.pos.y + player.height as f32 > tile.bb.origin.y as f32)
+    overlaps_area(player, tile.bb.origin, tile.bb.width, tile.bb.height)
+}
+
+fn overlaps_area(player: &Player, origin: Vector2, width: u32, height: u32) -> bool {
+    (player.pos.x < (origin.x + width as f32) as f32)
+        && (player.pos.x + player.width as f32 > origin.x as f32)
+        && (player.pos.y + 25.0 < (origin.y + height as f32) as f32)
+        && (player.pos.y + player.height as f32 > origin.y as f32)
 }
 
-fn handle_collision_x(player: &mut Player, tiles: &mut Vec<Tile>, move_fx: &Chunk, dt: f32) {
+fn is_area_occupied(players: &Vec<Player>, origin: Vector2, width: u32, height: u32) -> bool {
+    players.iter().any(|player| overlaps_area(player, origin, width, height))
+}
+
+fn players_collide(player: &Player, other: &Player) -> bool {
+    overlaps_area(player, Vector2 { x: other.pos.x, y: other.pos.y + 25.0 }, other.width, other.height - 25)
+}
+
+pub fn handle_teleports(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>) {
+    // Send the player across once their centre lands on a pad.
+    let center = player.get_center();
+    match tiles.iter().position(|tile| tile.isteleporter && contains_point(tile, center)) {
+        Some(pad) => {
+            if !player.teleported {
+                if let Some(partner) = find_partner(tiles, pad) {
+                    if is_pad_free(others, tiles, partner) {
+                        let dest = tiles[partner].bb.clone();
+                        player.place_on_tile(dest.origin, dest.width, dest.height);
+                        player.path.clear();
+                        player.teleported = true;
+                        tiles[pad].flash();
+                        tiles[partner].flash();
+                    }
+                }
+            }
+        }
+        None => player.teleported = false,
+    }
+
+    // Send blocks across once they come to rest on a pad.
+    for i in 0..tiles.len() {
+        if !tiles[i].isblock || tiles[i].moving || tiles[i].target_pos != tiles[i].bb.origin {
+            continue;
+        }
+        let origin = tiles[i].bb.origin;
+        match tiles.iter().position(|tile| tile.isteleporter && tile.bb.origin == origin) {
+            Some(pad) => {
+                if !tiles[i].teleported {
+                    if let Some(partner) = find_partner(tiles, pad) {
+                        if is_pad_free(others, tiles, partner)
+                            && !does_intersect(player, &mut tiles[partner].clone()) {
+                            let dest = tiles[partner].bb.origin;
+                            tiles[i].bb.origin = dest;
+                            tiles[i].target_pos = dest;
+                            tiles[i].teleported = true;
+                            tiles[pad].flash();
+                            tiles[partner].flash();
+                        }
+                    }
+                }
+            }
+            None => tiles[i].teleported = false,
+        }
+    }
+}
+
+// Tile-locked movement: move the player one cell along `step`, pushing the block
+// in front of them or, when `pulling`, dragging the block they hold after them.
+// Returns false if the way is blocked or a heavy block hasn't given way yet.
+pub fn try_step(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>, step: Vector2, allow_chain: bool,
+    pulling: bool, events: &mut Vec<MoveEvent>) -> bool {
+    let origin = get_cell_origin(get_cell(player.get_center()));
+    let dest = origin + step;
+    let direction = direction_of(step);
+    let is_taken = |point: Vector2| others.iter().any(|other| get_cell(other.get_center()) == get_cell(point));
+    if is_taken(dest) || tiles.iter().any(|tile| tile.bb.origin == dest && tile.blocks_entry(direction)) {
+        return false;
+    }
+
+    // Anything in the way has to be a single resting block that can be pushed on.
+    // A block sitting in a door of its own colour can be pushed back out of it.
+    let mut blockers: Vec<usize> = (0..tiles.len())
+        .filter(|&i| (tiles[i].bb.origin == dest || tiles[i].target_pos == dest) && tiles[i].blocks_player())
+        .collect();
+    if let Some(block) = blockers.iter().copied().find(|&i| tiles[i].isblock) {
+        blockers.retain(|&i| !tiles[i].isdoor || tiles[i].blocks_tile(&tiles[block]));
+    }
+    let mut pushed: Vec<usize> = Vec::new();
+    if let Some(&block) = blockers.first() {
+        if blockers.len() > 1 || !tiles[block].isblock || tiles[block].moving || tiles[block].falling > 0.0
+            || tiles[block].target_pos != dest {
+            return false;
+        }
+        if can_enter(tiles, block, step) && !is_taken(dest + step) {
+            pushed.push(block);
+        } else if allow_chain {
+            let next = tiles.iter().position(|tile| tile.isblock && !tile.moving
+                && tile.bb.origin == dest + step && tile.target_pos == dest + step);
+            match next {
+                Some(next) if can_enter(tiles, next, step) && !is_taken(dest + step + step) => {
+                    pushed.push(block);
+                    pushed.push(next);
+                }
+                _ => return false,
+            }
+        } else {
+            return false;
+        }
+    }
+
+    // Heavier pushes take more presses, or a longer hold. Each attempt wears one
+    // weight's worth off the front block's resistance, and a chained push has to
+    // get through the second block's as well. Blocks not being pushed start over.
+    for i in 0..tiles.len() {
+        if tiles[i].isblock && pushed.first() != Some(&i) {
+            tiles[i].reset_resistance();
+        }
+    }
+    if let Some(&block) = pushed.first() {
+        let threshold = match pushed.get(1) {
+            Some(&next) => -tiles[next].get_full_resistance(),
+            None => 0.0,
+        };
+        tiles[block].resistance -= BASE_RESISTANCE;
+        if tiles[block].resistance > threshold {
+            return false;
+        }
+    }
+    for &block in pushed.iter() {
+        tiles[block].target_pos = tiles[block].bb.origin + step;
+        tiles[block].reset_resistance();
+    }
+    if !pushed.is_empty() {
+        events.push(MoveEvent::Push);
+    }
+    // The player can't follow a block out of a door.
+    if tiles.iter().any(|tile| tile.isdoor && tile.bb.origin == dest) {
+        return true;
+    }
+
+    // A held block follows into the cell being left.
+    if pulling && blockers.is_empty() {
+        let facing = player.get_facing();
+        if facing.x * step.x + facing.y * step.y < 0.0 {
+            let ahead = origin - step;
+            if let Some(block) = tiles.iter().position(|tile| tile.isblock && !tile.moving
+                && tile.bb.origin == ahead && tile.target_pos == ahead) {
+                tiles[block].target_pos = origin;
+                events.push(MoveEvent::Push);
+            }
+        }
+    }
+
+    player.path = vec![player.get_tile_position(dest, TILE_WIDTH, TILE_HEIGHT)];
+    true
+}
+
+pub fn find_block_in_reach(player: &mut Player, tiles: &Vec<Tile>) -> Option<usize> {
+    let reach = player.get_reach();
+    tiles.iter().position(|tile| tile.isblock && contains_point(tile, reach))
+}
+
+// Drag a grabbed block one tile after the player while they back away from it.
+pub fn handle_pull(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>, block: usize,
+    events: &mut Vec<MoveEvent>, dt: f32) {
+    let facing = player.get_facing();
+    // Back the player out of the tile the block is being pulled into, keeping pace
+    // with it so it never lands on them and stays in reach.
+    let mut landing = tiles[block].clone();
+    landing.bb.origin = landing.target_pos;
+    if tiles[block].moving && does_intersect(player, &mut landing) {
+        player.vel = Vector2 { x: -facing.x * 200.0 * dt, y: -facing.y * 200.0 * dt };
+    }
+    let backwards = player.vel.x * facing.x + player.vel.y * facing.y < 0.0;
+    if !backwards || tiles[block].moving || tiles[block].falling > 0.0 {
+        tiles[block].reset_resistance();
+        return;
+    }
+
+    // The block moves into the player's tile, so the player needs room behind them.
+    let grabbed = tiles[block].clone();
+    let step = Vector2 { x: facing.x * grabbed.bb.width as f32, y: facing.y * grabbed.bb.height as f32 };
+    let dest = grabbed.bb.origin - step;
+    let behind = dest - step;
+    if tiles.iter().any(|tile| (tile.target_pos == dest && tile.blocks_tile(&grabbed))
+        || (tile.target_pos == behind && tile.blocks_player()))
+        || is_area_occupied(others, behind, grabbed.bb.width, grabbed.bb.height) {
+        return;
+    }
+
+    if tiles[block].resistance <= 0.0 {
+        tiles[block].target_pos = dest;
+        tiles[block].reset_resistance();
+        events.push(MoveEvent::Push);
+    } else {
+        tiles[block].resistance -= dt * 200.0;
+    }
+}
+
+pub fn contains_point(tile: &Tile, point: Vector2) -> bool {
+    point.x >= tile.bb.origin.x
+        && point.x < tile.bb.origin.x + tile.bb.width as f32
+        && point.y >= tile.bb.origin.y
+        && point.y < tile.bb.origin.y + tile.bb.height as f32
+}
+
+fn find_partner(tiles: &Vec<Tile>, pad: usize) -> Option<usize> {
+    tiles.iter().position(|tile| tile.isteleporter
+        && tile.channel == tiles[pad].channel
+        && tile.bb.origin != tiles[pad].bb.origin)
+}
+
+fn is_pad_free(players: &Vec<Player>, tiles: &Vec<Tile>, pad: usize) -> bool {
+    let bb = &tiles[pad].bb;
+    !tiles.iter().any(|tile| (tile.isblock || tile.iswall) && tile.target_pos == bb.origin)
+        && !is_area_occupied(players, bb.origin, bb.width, bb.height)
+}
+
+// Whether the player is stepping onto an arrow against its direction.
+fn is_entry_blocked(player: &mut Player, tile: &mut Tile, prev_pos: Vector2, direction: &str) -> bool {
+    if !tile.blocks_entry(direction) {
+        return false;
+    }
+    let pos = player.pos;
+    player.pos = prev_pos;
+    let was_on_tile = does_intersect(player, tile);
+    player.pos = pos;
+    !was_on_tile
+}
+
+fn handle_collision_x(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>, allow_chain: bool,
+    events: &mut Vec<MoveEvent>, dt: f32) {
     if player.vel.x != 0.0 {
+        let prev_pos = player.pos;
+        let direction = if player.vel.x > 0.0 { "right" } else { "left" };
         player.pos += Vector2{x: player.vel.x, y: 0.0};
-        for tile in tiles {
-            if tile.isblock || tile.iswall {
-                if does_intersect(player, tile) {
+        for i in 0..tiles.len() {
+            if tiles[i].blocks_player() || is_entry_blocked(player, &mut tiles[i], prev_pos, direction) {
+                if does_intersect(player, &mut tiles[i]) {
                     let dir: f32 = if player.vel.x > 0.0 { -1.0 } else { 1.0 };
-                    if tile.isblock && !tile.moving {
-                        if tile.resistance <= 0.0 {
-                            tile.target_pos.x = dir * -1.0 * tile.bb.width as f32 + tile.bb.origin.x;
-                            tile.resistance = 30.0;
-                        } else {
-                            tile.resistance -= dt * 200.0;
-                        }
+                    let step = Vector2 { x: dir * -1.0 * tiles[i].bb.width as f32, y: 0.0 };
+                    // Walking a click-to-move path never pushes anything.
+                    if player.path.is_empty() {
+                        push_block(tiles, others, i, step, allow_chain, events, dt);
                     }
+                    let tile = &tiles[i];
                     player.pos.x = if dir == 1.0 {
                         (tile.bb.origin.x + tile.bb.width as f32) as f32
                     } else {
                         (tile.bb.origin.x - player.width as f32) as f32
                     };
                 } else {
-                    tile.resistance = 30.0;
+                    tiles[i].reset_resistance();
                 }
             }
         }
+        for other in others {
+            if players_collide(player, other) {
+                player.pos.x = if player.vel.x > 0.0 {
+                    other.pos.x - player.width as f32
+                } else {
+                    other.pos.x + other.width as f32
+                };
+            }
+        }
         player.vel.x = 0.0;
     }
 }
 
-fn handle_collision_y(player: &mut Player, tiles: &mut Vec<Tile>, move_fx: &Chunk, dt: f32) {
+fn handle_collision_y(player: &mut Player, others: &Vec<Player>, tiles: &mut Vec<Tile>, allow_chain: bool,
+    events: &mut Vec<MoveEvent>, dt: f32) {
     if player.vel.y != 0.0 {
+        let prev_pos = player.pos;
+        let direction = if player.vel.y > 0.0 { "down" } else { "up" };
         player.pos += Vector2{x: 0.0, y: player.vel.y};
-        for tile in tiles {
-            if tile.isblock || tile.iswall {
-                if does_intersect(player, tile) {
+        for i in 0..tiles.len() {
+            if tiles[i].blocks_player() || is_entry_blocked(player, &mut tiles[i], prev_pos, direction) {
+                if does_intersect(player, &mut tiles[i]) {
                     let dir: f32 = if player.vel.y > 0.0 { -1.0 } else { 1.0 };
-                    if tile.isblock && !tile.moving {
-                        if tile.resistance <= 0.0 {
-                            tile.target_pos.y = dir * -1.0 * tile.bb.height as f32 + tile.bb.origin.y;
-                            tile.resistance = 30.0;
-                        } else {
-                            tile.resistance -= dt * 200.0;
-                        }
+                    let step = Vector2 { x: 0.0, y: dir * -1.0 * tiles[i].bb.height as f32 };
+                    if player.path.is_empty() {
+                        push_block(tiles, others, i, step, allow_chain, events, dt);
                     }
+                    let tile = &tiles[i];
                     player.pos.y = if dir == 1.0 {
                         -25.0 + (tile.bb.origin.y + tile.bb.height as f32) as f32
                     } else {
                         (tile.bb.origin.y - player.height as f32) as f32
                     };
                 } else {
-                    tile.resistance = 30.0;
+                    tiles[i].reset_resistance();
                 }
             }
         }
+        for other in others {
+            if players_collide(player, other) {
+                player.pos.y = if player.vel.y > 0.0 {
+                    other.pos.y + 25.0 - player.height as f32
+                } else {
+                    other.pos.y + other.height as f32 - 25.0
+                };
+            }
+        }
         player.vel.y = 0.0;
     }
 }
+
+// Push a block one tile along `step` once the player has leaned on it long enough.
+// Where the level allows it, a block with another resting behind it takes both
+// blocks' resistance to shift and moves them together.
+fn push_block(tiles: &mut Vec<Tile>, others: &Vec<Player>, index: usize, step: Vector2, allow_chain: bool,
+    events: &mut Vec<MoveEvent>, dt: f32) {
+    if !tiles[index].isblock || tiles[index].moving || tiles[index].falling > 0.0 {
+        return;
+    }
+    let dest = tiles[index].bb.origin + step;
+    let (width, height) = (tiles[index].bb.width, tiles[index].bb.height);
+    if is_area_occupied(others, dest, width, height) {
+        return;
+    }
+    let chained = if allow_chain {
+        tiles.iter()
+            .position(|tile| tile.isblock && !tile.moving && tile.bb.origin == dest && tile.target_pos == dest)
+            .filter(|&next| can_enter(tiles, next, step) && !is_area_occupied(others, dest + step, width, height))
+    } else {
+        None
+    };
+    // A block with nowhere to go stays put, so leaning on it doesn't count as pushes.
+    if chained.is_none() && !can_enter(tiles, index, step) {
+        return;
+    }
+    let threshold = match chained {
+        Some(next) => -tiles[next].get_full_resistance(),
+        None => 0.0,
+    };
+    if tiles[index].resistance <= threshold {
+        tiles[index].target_pos = dest;
+        tiles[index].reset_resistance();
+        if let Some(next) = chained {
+            tiles[next].target_pos = dest + step;
+        }
+        events.push(MoveEvent::Push);
+    } else {
+        tiles[index].resistance -= dt * 200.0;
+    }
+}
+
+// Whether a block could be pushed one tile along `step` without being stopped.
+fn can_enter(tiles: &Vec<Tile>, block: usize, step: Vector2) -> bool {
+    let dest = tiles[block].bb.origin + step;
+    let direction = direction_of(step);
+    !tiles.iter().any(|tile| tile.target_pos == dest
+        && (tile.blocks_tile(&tiles[block]) || tile.blocks_entry(direction)))
+}
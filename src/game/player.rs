@@ -3,16 +3,19 @@ use barn::graphics::color::Color;
 use barn::graphics::SdlTexture;
 use barn::graphics::barn_gfx::BarnGFX;
 use barn::graphics::fill_type::FillType;
-use barn::input::SdlKeycode;
 use barn::math::bounding_box_2d::BoundingBox2D;
 use barn::math::vector2::Vector2;
 use barn::input::keyboard_handler::KeyboardHandler;
 
 use crate::game::camera::Camera;
+use crate::game::controls::{is_pressed, Action};
 use crate::settings;
 
 use std::collections::HashMap;
 
+const REACH: f32 = 48.0;
+const SPEED: f32 = 225.0;
+
 pub struct Player {
     pub width: u32,
     pub height: u32,
@@ -21,6 +24,9 @@ pub struct Player {
     pub xrect: i32,
     pub delay: f32,
     pub frame: i32,
+    pub teleported: bool,
+    pub grabbing: bool,
+    pub path: Vec<Vector2>,
     pub active_animation: String,
     pub animations: HashMap<String, Vec<SdlRect>>
 }
@@ -35,6 +41,9 @@ impl Player {
             xrect: 0,
             delay: 13.0 / 60.0,
             frame: 0,
+            teleported: false,
+            grabbing: false,
+            path: Vec::new(),
             active_animation: String::from("walk_down"),
             animations: Player::generate_animations(),
         }
@@ -44,6 +53,68 @@ impl Player {
         BoundingBox2D { origin: self.pos, width: self.width, height: self.height }
     }
 
+    pub fn get_center(&self) -> Vector2 {
+        Vector2 { x: self.pos.x + self.width as f32 / 2.0, y: self.pos.y + self.height as f32 / 2.0 }
+    }
+
+    // Stand idle facing the same way, e.g. when control passes to another player.
+    pub fn stop(&mut self) {
+        self.vel = Vector2::ZERO;
+        self.active_animation = self.active_animation.replace("walk", "idle");
+        self.frame = 0;
+    }
+
+    // Unit vector for the direction the player is facing.
+    pub fn get_facing(&mut self) -> Vector2 {
+        if self.active_animation.ends_with("left") {
+            Vector2 { x: -1.0, y: 0.0 }
+        } else if self.active_animation.ends_with("right") {
+            Vector2 { x: 1.0, y: 0.0 }
+        } else if self.active_animation.ends_with("up") {
+            Vector2 { x: 0.0, y: -1.0 }
+        } else {
+            Vector2 { x: 0.0, y: 1.0 }
+        }
+    }
+
+    // Point just in front of the player, inside whatever tile they are facing.
+    pub fn get_reach(&mut self) -> Vector2 {
+        let facing = self.get_facing();
+        let center = self.get_center();
+        Vector2 { x: center.x + facing.x * REACH, y: center.y + facing.y * REACH }
+    }
+
+    // Place the player on the tile whose top-left corner is at the given position.
+    pub fn place_on_tile(&mut self, origin: Vector2, tile_width: u32, tile_height: u32) {
+        self.pos = self.get_tile_position(origin, tile_width, tile_height);
+    }
+
+    // Where the player stands when on the tile at the given position.
+    pub fn get_tile_position(&self, origin: Vector2, tile_width: u32, tile_height: u32) -> Vector2 {
+        Vector2 {
+            x: origin.x + (tile_width / 2) as f32 - (self.width / 2) as f32,
+            y: origin.y + 3.0 - (tile_height / 2) as f32 + (self.height / 2) as f32,
+        }
+    }
+
+    // Walk towards the next point of a click-to-move path, one axis at a time.
+    fn follow_path(&mut self, dt: f32) {
+        while let Some(target) = self.path.first().copied() {
+            let (dx, dy) = (target.x - self.pos.x, target.y - self.pos.y);
+            if dx.abs() > 0.5 {
+                self.vel.x = dx.signum() * (SPEED * dt).min(dx.abs());
+                self.active_animation = String::from(if dx > 0.0 { "walk_right" } else { "walk_left" });
+                return;
+            } else if dy.abs() > 0.5 {
+                self.vel.y = dy.signum() * (SPEED * dt).min(dy.abs());
+                self.active_animation = String::from(if dy > 0.0 { "walk_down" } else { "walk_up" });
+                return;
+            }
+            self.pos = target;
+            self.path.remove(0);
+        }
+    }
+
     pub fn generate_animations() -> HashMap<String, Vec<SdlRect>> {
         // Generate animation frames.
         let mut result: HashMap<String, Vec<SdlRect>> = HashMap::new();
@@ -89,33 +160,33 @@ impl Player {
     pub fn update(&mut self, input: &mut KeyboardHandler, dt: f32) {
         // Update movement
         let prev_anim = self.active_animation.clone();
-        if input.key_pressed(&SdlKeycode::Left) && !input.key_pressed(&SdlKeycode::Right) {
-            self.vel.x -= 225.0 * dt;
+        if is_pressed(input, Action::MoveLeft) && !is_pressed(input, Action::MoveRight) {
+            self.vel.x -= SPEED * dt;
             if self.active_animation != "walk_left" {
                 self.active_animation = String::from("walk_left");
             }
-        } else if input.key_pressed(&SdlKeycode::Right) && !input.key_pressed(&SdlKeycode::Left) {
-            self.vel.x += 225.0 * dt;
+        } else if is_pressed(input, Action::MoveRight) && !is_pressed(input, Action::MoveLeft) {
+            self.vel.x += SPEED * dt;
             if self.active_animation != "walk_right" {
                 self.active_animation = String::from("walk_right");
             }
         }
-        if input.key_pressed(&SdlKeycode::Up) && !input.key_pressed(&SdlKeycode::Down) {
-            self.vel.y -= 225.0 * dt;
+        if is_pressed(input, Action::MoveUp) && !is_pressed(input, Action::MoveDown) {
+            self.vel.y -= SPEED * dt;
             if self.active_animation != "walk_up" {
                 self.active_animation = String::from("walk_up");
             }
-        } else if input.key_pressed(&SdlKeycode::Down) && !input.key_pressed(&SdlKeycode::Up) {
-            self.vel.y += 225.0 * dt;
+        } else if is_pressed(input, Action::MoveDown) && !is_pressed(input, Action::MoveUp) {
+            self.vel.y += SPEED * dt;
             if self.active_animation != "walk_down" {
                 self.active_animation = String::from("walk_down");
             }
         }
-        if !((input.key_pressed(&SdlKeycode::Left) && !input.key_pressed(&SdlKeycode::Right))
-            || (input.key_pressed(&SdlKeycode::Right) && !input.key_pressed(&SdlKeycode::Left))
-            || (input.key_pressed(&SdlKeycode::Up) && !input.key_pressed(&SdlKeycode::Down))
-            || (input.key_pressed(&SdlKeycode::Down) && !input.key_pressed(&SdlKeycode::Up)))
-        {
+        let moving = (is_pressed(input, Action::MoveLeft) && !is_pressed(input, Action::MoveRight))
+            || (is_pressed(input, Action::MoveRight) && !is_pressed(input, Action::MoveLeft))
+            || (is_pressed(input, Action::MoveUp) && !is_pressed(input, Action::MoveDown))
+            || (is_pressed(input, Action::MoveDown) && !is_pressed(input, Action::MoveUp));
+        if !moving {
             if self.active_animation == "walk_left" {
                 self.active_animation = String::from("idle_left")
             } else if self.active_animation == "walk_right" {
@@ -126,6 +197,39 @@ impl Player {
                 self.active_animation = String::from("idle_down")
             }
         }
+        // Moving by hand cancels any click-to-move path.
+        if moving {
+            self.path.clear();
+        } else {
+            self.follow_path(dt);
+        }
+        self.animate(&prev_anim, dt);
+    }
+
+    // Tile-locked movement: walk to the cell stepped into, then stand idle.
+    pub fn update_grid(&mut self, dt: f32) {
+        let prev_anim = self.active_animation.clone();
+        if self.path.is_empty() {
+            self.active_animation = self.active_animation.replace("walk", "idle");
+        }
+        self.follow_path(dt);
+        self.animate(&prev_anim, dt);
+    }
+
+    // Turn to face a direction without moving, e.g. after bumping into a wall.
+    pub fn face(&mut self, direction: &str) {
+        if !self.grabbing {
+            self.active_animation = format!("idle_{}", direction);
+        }
+    }
+
+    fn animate(&mut self, prev_anim: &str, dt: f32) {
+        // Keep facing the grabbed block while walking backwards.
+        if self.grabbing {
+            let facing = prev_anim.split('_').last().unwrap_or("down").to_string();
+            let action = if self.active_animation.starts_with("walk") { "walk" } else { "idle" };
+            self.active_animation = format!("{}_{}", action, facing);
+        }
         if self.active_animation != prev_anim {
             self.frame = 0;
             self.delay = 13.0 / 60.0;
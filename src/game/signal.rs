@@ -0,0 +1,60 @@
+use crate::game::physics::{contains_point, does_intersect};
+use crate::game::player::Player;
+use crate::game::tile::Tile;
+
+use std::collections::HashMap;
+
+// Plates and switches drive a signal on their channel, which opens every gate
+// listening on that channel. The channel is on while any of its plates is held,
+// then each switch that is flipped on inverts it.
+pub fn update_signals(players: &mut Vec<Player>, tiles: &mut Vec<Tile>) {
+    let mut plates: HashMap<u32, bool> = HashMap::new();
+    let mut switches: HashMap<u32, bool> = HashMap::new();
+    for i in 0..tiles.len() {
+        if !tiles[i].isplate && !tiles[i].isswitch {
+            continue;
+        }
+        let was_pressed = tiles[i].pressed;
+        tiles[i].pressed = is_weighed_down(players, tiles, i);
+        if tiles[i].isplate {
+            tiles[i].active = tiles[i].pressed;
+        } else if tiles[i].pressed && !was_pressed {
+            tiles[i].active = !tiles[i].active;
+        }
+        tiles[i].frame = tiles[i].active as i32;
+        if tiles[i].isplate {
+            let held = plates.entry(tiles[i].channel).or_insert(false);
+            *held = *held || tiles[i].active;
+        } else {
+            let flipped = switches.entry(tiles[i].channel).or_insert(false);
+            *flipped = *flipped != tiles[i].active;
+        }
+    }
+
+    for i in 0..tiles.len() {
+        if !tiles[i].isgate {
+            continue;
+        }
+        let channel = tiles[i].channel;
+        let open = *plates.get(&channel).unwrap_or(&false) != *switches.get(&channel).unwrap_or(&false);
+        // A gate cannot shut on top of the player or a block.
+        if !open && tiles[i].active && is_occupied(players, tiles, i) {
+            continue;
+        }
+        tiles[i].active = open;
+        tiles[i].iswall = !open;
+        tiles[i].frame = open as i32;
+    }
+}
+
+fn is_weighed_down(players: &mut Vec<Player>, tiles: &Vec<Tile>, index: usize) -> bool {
+    let origin = tiles[index].bb.origin;
+    tiles.iter().any(|tile| tile.isblock && tile.bb.origin == origin)
+        || players.iter_mut().any(|player| contains_point(&tiles[index], player.get_center()))
+}
+
+fn is_occupied(players: &mut Vec<Player>, tiles: &Vec<Tile>, index: usize) -> bool {
+    let mut gate = tiles[index].clone();
+    tiles.iter().any(|tile| tile.isblock && tile.bb.clone().intersects_box(&mut gate.bb))
+        || players.iter_mut().any(|player| does_intersect(player, &mut gate))
+}
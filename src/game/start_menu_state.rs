@@ -1,21 +1,21 @@
-use barn::audio::load_music;
 use barn::graphics::barn_gfx::BarnGFX;
 use barn::game::barn_context::BarnContext;
 use barn::graphics::color::Color;
 use barn::graphics::fill_type::FillType;
 use barn::math::vector2::Vector2;
-use barn::math::bounding_box_2d::BoundingBox2D;
 use barn::game::state::State;
+use crate::game::audio;
 use crate::game::camera::Camera;
+use crate::game::controls::{self, is_just_pressed, Action};
 use crate::game::credits_state::CreditsState;
 use crate::game::eye::Eye;
 use crate::game::help_state::HelpState;
 use crate::game::level_select_state::LevelSelectState;
+use crate::game::mouse;
+use crate::game::options_state::OptionsState;
 use crate::game::tile::Tile;
 use crate::settings;
 
-use sdl2::keyboard::Keycode;
-
 use std::collections::HashMap;
 
 pub struct StartMenuState {
@@ -38,71 +38,73 @@ impl StartMenuState {
 
 impl State<BarnContext> for StartMenuState {
     fn update(&mut self, context: &mut BarnContext, dt: f32) -> Option<Box<dyn State<BarnContext>>> {
+        controls::update();
         for eye in self.eyes.iter_mut() {
             eye.update(&mut self.tiles, dt);
         }
         let prev_option = self.selected_option;
-        if context.input.key_just_pressed(&Keycode::Down) {
-            if self.selected_option == 2 {
+        if is_just_pressed(&context.input, Action::MoveDown) {
+            if self.selected_option == 3 {
                 self.selected_option = 0;
             } else {
                 self.selected_option += 1;
             }
-        } else if context.input.key_just_pressed(&Keycode::Up) {
+        } else if is_just_pressed(&context.input, Action::MoveUp) {
             if self.selected_option == 0 {
-                self.selected_option = 2;
+                self.selected_option = 3;
             } else {
                 self.selected_option -= 1;
             }
         }
+
+        // Select the option under the cursor; clicking it enters it.
+        let hovered = (0..4).find(|i| mouse::is_over(self.camera.width / 2 - 125, 200 + *i as i32 * 100, 250, 65));
+        if let Some(option) = hovered {
+            if mouse::has_moved() || mouse::is_clicked() {
+                self.selected_option = option;
+            }
+        }
+        let clicked = hovered.is_some() && mouse::is_clicked();
        
         if prev_option != self.selected_option {
-            if settings::ENABLE_SOUND {
-                let channel = sdl2::mixer::Channel(1);
-                let select_fx = context.load_sound(String::from("res/sound/select.ogg"));
-                channel.play(select_fx, 0).unwrap();
-            }
+            audio::play_sound(context, "res/sound/select.ogg");
             
             self.tiles.clear();
             if self.selected_option == 0 {
                 self.tiles.push(Tile {
-                    texture: String::from("res/img/blueblock.png"),
-                    bb: BoundingBox2D {origin: Vector2 {x: 200.0, y: 200.0}, width: 64, height: 64},
-                    target_pos: Vector2 {x: 200.0, y: 200.0},
-                    resistance: 30.0,
-                    iswall: false,
                     isblock: true,
-                    moving: false, 
+                    color: String::from("blue"),
+                    ..Tile::new("res/img/blueblock.png", 200.0, 200.0, 64, 64)
                 });
             } else if self.selected_option == 1 {
                 self.tiles.push(Tile {
-                    texture: String::from("res/img/greenblock.png"),
-                    bb: BoundingBox2D {origin: Vector2 {x: 200.0, y: 300.0}, width: 64, height: 64},
-                    target_pos: Vector2 {x: 200.0, y: 500.0},
-                    resistance: 30.0,
-                    iswall: false,
                     isblock: true,
-                    moving: false, 
+                    color: String::from("green"),
+                    target_pos: Vector2 {x: 200.0, y: 500.0},
+                    ..Tile::new("res/img/greenblock.png", 200.0, 300.0, 64, 64)
                 });
             } else if self.selected_option == 2 {
                 self.tiles.push(Tile {
-                    texture: String::from("res/img/redblock.png"),
-                    bb: BoundingBox2D {origin: Vector2 {x: (self.camera.width / 2 - 32) as f32, y: 300.0}, width: 64, height: 64},
+                    isblock: true,
+                    color: String::from("red"),
                     target_pos: Vector2 {x: 200.0, y: 200.0},
-                    resistance: 30.0,
-                    iswall: false,
+                    ..Tile::new("res/img/redblock.png", (self.camera.width / 2 - 32) as f32, 300.0, 64, 64)
+                });
+            } else if self.selected_option == 3 {
+                self.tiles.push(Tile {
                     isblock: true,
-                    moving: false, 
+                    color: String::from("gray"),
+                    ..Tile::new("res/img/grayblock.png", 200.0, 500.0, 64, 64)
                 });
             }
         }
        
-        if context.input.key_just_pressed(&Keycode::Return) {
-            if settings::ENABLE_SOUND {
-                let enter_fx = context.load_sound(String::from("res/sound/enter.ogg"));
-                let channel = sdl2::mixer::Channel(2);
-                channel.play(enter_fx, 0).unwrap();
-            }
+        if is_just_pressed(&context.input, Action::Options) {
+            return Some(Box::new(OptionsState::new()));
+        }
+
+        if is_just_pressed(&context.input, Action::Confirm) || clicked {
+            audio::play_sound(context, "res/sound/enter.ogg");
             
             if self.selected_option == 0 {
                 return Some(Box::new(LevelSelectState {
@@ -121,6 +123,8 @@ impl State<BarnContext> for StartMenuState {
                 return Some(Box::new(CreditsState {
                     camera: Camera::new(),
                 }));
+            } else if self.selected_option == 3 {
+                return Some(Box::new(OptionsState::new()));
             }
         }
 
@@ -151,8 +155,10 @@ impl State<BarnContext> for StartMenuState {
         } else {
             if self.selected_option == 1 {
                 Color::from_rgb(0, 180, 0)
-            } else {
+            } else if self.selected_option == 2 {
                 Color::from_rgb(180, 0, 0)
+            } else {
+                Color::from_rgb(100, 100, 100)
             }
         };
         bgfx.sdl.set_draw_color(color);
@@ -170,6 +176,7 @@ impl State<BarnContext> for StartMenuState {
         bgfx.sdl.draw_rect(self.camera.width / 2 - 125, 200, 250, 65, FillType::LINE, false);
         bgfx.sdl.draw_rect(self.camera.width / 2 - 125, 300, 250, 65, FillType::LINE, false);
         bgfx.sdl.draw_rect(self.camera.width / 2 - 125, 400, 250, 65, FillType::LINE, false);
+        bgfx.sdl.draw_rect(self.camera.width / 2 - 125, 500, 250, 65, FillType::LINE, false);
        
         // Render the options.
         bgfx.sdl.set_draw_color(Color::WHITE);
@@ -196,6 +203,14 @@ impl State<BarnContext> for StartMenuState {
             3.0,
             true,
             false);
+
+        bgfx.sdl.draw_text("Options", font, 
+            self.camera.width as f32 / 2.0,
+            500.0,
+            3.0,
+            3.0,
+            true,
+            false);
         
         // Render menu eyes.
         for eye in self.eyes.iter_mut() {
@@ -261,41 +276,35 @@ impl State<BarnContext> for StartMenuState {
         });
         if self.selected_option == 0 {
             self.tiles.push(Tile {
-                texture: String::from("res/img/blueblock.png"),
-                bb: BoundingBox2D {origin: Vector2 {x: 200.0, y: 200.0}, width: 64, height: 64},
-                target_pos: Vector2 {x: 200.0, y: 200.0},
-                resistance: 30.0,
-                iswall: false,
                 isblock: true,
-                moving: false, 
+                color: String::from("blue"),
+                ..Tile::new("res/img/blueblock.png", 200.0, 200.0, 64, 64)
             });
         } else if self.selected_option == 1 {
             self.tiles.push(Tile {
-                texture: String::from("res/img/greenblock.png"),
-                bb: BoundingBox2D {origin: Vector2 {x: 200.0, y: 300.0}, width: 64, height: 64},
-                target_pos: Vector2 {x: 200.0, y: 500.0},
-                resistance: 30.0,
-                iswall: false,
                 isblock: true,
-                moving: false, 
+                color: String::from("green"),
+                target_pos: Vector2 {x: 200.0, y: 500.0},
+                ..Tile::new("res/img/greenblock.png", 200.0, 300.0, 64, 64)
             });
         } else if self.selected_option == 2 {
             self.tiles.push(Tile {
-                texture: String::from("res/img/redblock.png"),
-                bb: BoundingBox2D {origin: Vector2 {x: (self.camera.width / 2 - 32) as f32, y: 300.0}, width: 64, height: 64},
+                isblock: true,
+                color: String::from("red"),
                 target_pos: Vector2 {x: 200.0, y: 200.0},
-                resistance: 30.0,
-                iswall: false,
+                ..Tile::new("res/img/redblock.png", (self.camera.width / 2 - 32) as f32, 300.0, 64, 64)
+            });
+        } else if self.selected_option == 3 {
+            self.tiles.push(Tile {
                 isblock: true,
-                moving: false, 
+                color: String::from("gray"),
+                ..Tile::new("res/img/grayblock.png", 200.0, 500.0, 64, 64)
             });
         }
         
         self.camera.x = 0;
         self.camera.y = 0;
-        //let music = context.load_sound(String::from("res/sound/laidback.mp3"));
-        //let _ = load_music(String::from("res/sound/laidback.wav"));
-
+        audio::play_music(context, audio::MENU_MUSIC);
     }
 
     fn on_exit(&mut self, context: &mut BarnContext) {
@@ -4,26 +4,81 @@ use barn::graphics::barn_gfx::BarnGFX;
 use barn::graphics::fill_type::FillType;
 use barn::math::vector2::Vector2;
 use barn::math::bounding_box_2d::BoundingBox2D;
+use crate::game::audio;
 use crate::game::camera::Camera;
 use crate::settings;
 use sdl2::mixer::Chunk;
 
 use sdl2::rect::Rect;
 
+pub const FALL_TIME: f32 = 0.4;
+pub const BASE_RESISTANCE: f32 = 30.0;
+const TELEPORTER_FRAMES: i32 = 3;
+const CHANNEL_COLORS: [(u8, u8, u8); 4] = [(120, 200, 255), (255, 140, 220), (255, 220, 100), (140, 255, 160)];
+
 #[derive(Clone)]
 pub struct Tile {
     pub texture: String,
+    pub color: String,
+    pub direction: String,
     pub bb: BoundingBox2D,
     pub target_pos: Vector2,
     pub resistance: f32,
+    pub weight: u32,
     pub iswall: bool,
     pub isblock: bool,
-    pub moving: bool
+    pub isteleporter: bool,
+    pub isplate: bool,
+    pub isswitch: bool,
+    pub isgate: bool,
+    pub isdoor: bool,
+    pub ispit: bool,
+    pub isarrow: bool,
+    pub ismirror: bool,
+    pub rotatable: bool,
+    pub moving: bool,
+    pub channel: u32,
+    pub active: bool,
+    pub pressed: bool,
+    pub falling: f32,
+    pub teleported: bool,
+    pub delay: i32,
+    pub frame: i32,
 }
 
 impl Tile {
+    pub fn new(texture: &str, x: f32, y: f32, width: u32, height: u32) -> Tile {
+        Tile {
+            texture: String::from(texture),
+            color: String::new(),
+            direction: String::new(),
+            bb: BoundingBox2D {origin: Vector2 {x: x, y: y}, width: width, height: height},
+            target_pos: Vector2 {x: x, y: y},
+            resistance: BASE_RESISTANCE,
+            weight: 1,
+            iswall: false,
+            isblock: false,
+            isteleporter: false,
+            isplate: false,
+            isswitch: false,
+            isgate: false,
+            isdoor: false,
+            ispit: false,
+            isarrow: false,
+            ismirror: false,
+            rotatable: false,
+            moving: false,
+            channel: 0,
+            active: false,
+            pressed: false,
+            falling: 0.0,
+            teleported: false,
+            delay: 10,
+            frame: 0,
+        }
+    }
 
-    pub fn update(&mut self, tiles: &Vec<Tile>, move_fx: &Chunk, dt: f32) {
+    pub fn update(&mut self, tiles: &Vec<Tile>, move_fx: &Chunk, camera: &Camera, dt: f32) {
         let prev_pos = self.bb.origin.clone();
         let delta = 200.0;
         let prev_moving = self.moving;
@@ -39,32 +94,68 @@ impl Tile {
                 self.bb.origin = self.target_pos;
             }
         }
-        // Prevent tile from moving into wall or another block
+        // Prevent tile from moving into wall or another block, or against an arrow.
+        let direction = direction_of(self.bb.origin - prev_pos);
         for tile in tiles {
-            if (tile.iswall || tile.isblock)
+            if (tile.blocks_tile(self) || (tile.bb.origin == self.target_pos && tile.blocks_entry(direction)))
+            && !self.is_chained_behind(tile)
             && tile.bb.origin != self.bb.origin
             && tile.bb.origin != prev_pos
             && self.bb.intersects_box(&mut tile.bb.clone()) {
                 //self.bb.origin = prev_pos.clone();
-                self.resistance = 30.0;
+                self.reset_resistance();
                 self.bb.origin = prev_pos;
                 self.target_pos = prev_pos;
                 self.moving = false;
             }
         }
 
-        if settings::ENABLE_SOUND && !prev_moving && self.moving {
-            let channel = sdl2::mixer::Channel(0);
-            channel.play(&move_fx, 0);     
+        if !prev_moving && self.moving {
+            audio::play_world_sound(move_fx,
+                self.bb.origin.x + self.bb.width as f32 / 2.0,
+                self.bb.origin.y + self.bb.height as f32 / 2.0,
+                camera);
+        }
+
+        // Cycle the teleporter pad glow.
+        if self.isteleporter {
+            self.delay -= 1;
+            if self.delay <= 0 {
+                self.frame = (self.frame + 1) % TELEPORTER_FRAMES;
+                self.delay = 10;
+            }
         }
     }
 
+    // Hold the brightest teleporter frame for a moment after a jump.
+    pub fn flash(&mut self) {
+        self.frame = TELEPORTER_FRAMES - 1;
+        self.delay = 30;
+    }
+
     pub fn draw(&mut self, texture: &mut SdlTexture, camera: &mut Camera, bgfx: &mut BarnGFX) {
-        bgfx.sdl.draw_texture(texture,  None, Some(Rect::new(
-            self.bb.origin.x.round() as i32 - camera.x,
-            self.bb.origin.y.round() as i32 - camera.y,
-            self.bb.width as u32,
-            self.bb.height as u32,
+        let mut src = None;
+        if self.isteleporter || self.isplate || self.isswitch || self.isgate {
+            // Tint linked tiles by channel so links can be told apart.
+            let (r, g, b) = CHANNEL_COLORS[self.channel as usize % CHANNEL_COLORS.len()];
+            texture.set_color_mod(r, g, b);
+            src = Some(Rect::new(self.frame * self.bb.width as i32, 0, self.bb.width, self.bb.height));
+        } else if self.ismirror {
+            src = Some(Rect::new(self.frame * self.bb.width as i32, 0, self.bb.width, self.bb.height));
+        } else if self.isblock {
+            // Heavier blocks are drawn darker.
+            let shade = 255 - 40 * (self.weight.min(4) - 1) as u8;
+            texture.set_color_mod(shade, shade, shade);
+        }
+        // Shrink blocks towards their centre as they fall into a pit.
+        let scale = 1.0 - (self.falling / FALL_TIME).min(1.0);
+        let width = self.bb.width as f32 * scale;
+        let height = self.bb.height as f32 * scale;
+        bgfx.sdl.draw_texture(texture,  src, Some(Rect::new(
+            (self.bb.origin.x + (self.bb.width as f32 - width) / 2.0).round() as i32 - camera.x,
+            (self.bb.origin.y + (self.bb.height as f32 - height) / 2.0).round() as i32 - camera.y,
+            width.round() as u32,
+            height.round() as u32,
         )));
         
         // Render the collision box.
@@ -83,9 +174,67 @@ impl Tile {
         }
     }
 
-    pub fn has_moved(&mut self) -> bool {
-        (self.target_pos.y - self.bb.origin.y).abs() == self.bb.height as f32
-            || (self.target_pos.x - self.bb.origin.x).abs() == self.bb.width as f32
+    pub fn get_full_resistance(&self) -> f32 {
+        BASE_RESISTANCE * self.weight as f32
+    }
+
+    pub fn reset_resistance(&mut self) {
+        self.resistance = self.get_full_resistance();
+    }
+
+    pub fn get_push_sound(&self) -> String {
+        String::from(match self.weight {
+            1 => "res/sound/push.ogg",
+            2 => "res/sound/push_heavy.wav",
+            _ => "res/sound/push_heaviest.wav",
+        })
+    }
+
+    // Whether `other` is the block directly ahead of this one in a chained push.
+    fn is_chained_behind(&self, other: &Tile) -> bool {
+        let gap = other.target_pos - self.target_pos;
+        let one_tile = (gap.x.abs() == self.bb.width as f32 && gap.y == 0.0)
+            || (gap.y.abs() == self.bb.height as f32 && gap.x == 0.0);
+        other.isblock
+            && one_tile
+            && other.target_pos != other.bb.origin
+            && self.target_pos != self.bb.origin
+            && direction_of(gap) == direction_of(self.target_pos - self.bb.origin)
+    }
+
+    pub fn blocks_player(&self) -> bool {
+        self.iswall || self.isblock || self.isdoor || self.ispit
+    }
+
+    // Doors let through blocks of their own colour.
+    pub fn blocks_tile(&self, other: &Tile) -> bool {
+        self.iswall || self.isblock || (self.isdoor && self.color != other.color)
+    }
+
+    pub fn blocks_sight(&self) -> bool {
+        self.iswall || self.isblock || self.isteleporter
+    }
+
+    // Swap a mirror between `/` and `\`.
+    pub fn rotate_mirror(&mut self) {
+        self.direction = String::from(if self.direction == "/" { "\\" } else { "/" });
+        self.frame = self.mirror_frame();
+    }
+
+    pub fn mirror_frame(&self) -> i32 {
+        (self.direction != "/") as i32 + if self.rotatable { 2 } else { 0 }
+    }
+
+    // Arrows can only be entered while moving in the direction they point.
+    pub fn blocks_entry(&self, direction: &str) -> bool {
+        self.isarrow && self.direction != direction
     }
 }
 
+pub fn direction_of(delta: Vector2) -> &'static str {
+    if delta.x.abs() >= delta.y.abs() {
+        if delta.x > 0.0 { "right" } else { "left" }
+    } else {
+        if delta.y > 0.0 { "down" } else { "up" }
+    }
+}
@@ -0,0 +1,120 @@
+use barn::game::barn_context::BarnContext;
+use barn::graphics::barn_gfx::BarnGFX;
+use barn::math::vector2::Vector2;
+use crate::game::camera::Camera;
+use crate::game::eye::cast_ray;
+use crate::game::player::Player;
+use crate::game::tile::Tile;
+
+use sdl2::rect::Rect;
+
+pub struct Watcher {
+    pub pos: Vector2,
+    pub target_pos: Vector2,
+    pub width: u32,
+    pub height: u32,
+    pub direction: String,
+    pub speed: f32,
+}
+
+impl Watcher {
+    pub fn new(x: f32, y: f32, width: u32, height: u32, direction: &str) -> Watcher {
+        Watcher {
+            pos: Vector2 { x: x, y: y },
+            target_pos: Vector2 { x: x, y: y },
+            width: width,
+            height: height,
+            direction: String::from(direction),
+            speed: 96.0,
+        }
+    }
+
+    // Walk back and forth along a line, turning around at anything in the way.
+    pub fn update(&mut self, tiles: &Vec<Tile>, dt: f32) {
+        if self.pos == self.target_pos {
+            let mut next = self.get_next_tile();
+            if !Watcher::is_walkable(tiles, next) {
+                self.direction = String::from(match self.direction.as_str() {
+                    "left" => "right",
+                    "right" => "left",
+                    "up" => "down",
+                    _ => "up",
+                });
+                next = self.get_next_tile();
+            }
+            if Watcher::is_walkable(tiles, next) {
+                self.target_pos = next;
+            }
+        }
+
+        let mut diff = self.target_pos - self.pos;
+        let dist = diff.length();
+        if self.speed * dt < dist {
+            self.pos += diff.normalize() * self.speed * dt;
+        } else {
+            self.pos = self.target_pos;
+        }
+    }
+
+    // Whether the player is standing in this watcher's line of sight.
+    pub fn can_see(&mut self, player: &mut Player, tiles: &Vec<Tile>) -> bool {
+        // Look out from whichever tile the watcher is mostly over.
+        let from = if (self.target_pos - self.pos).length() < self.width as f32 / 2.0 {
+            self.target_pos
+        } else {
+            self.target_pos - self.get_step()
+        };
+        let step = self.get_step();
+        let sight = cast_ray(tiles, from.x, from.y, step.x / self.width as f32, step.y / self.height as f32);
+        for segment in sight.path.windows(2) {
+            let left = segment[0].x.min(segment[1].x);
+            let top = segment[0].y.min(segment[1].y);
+            let right = segment[0].x.max(segment[1].x) + self.width as f32;
+            let bottom = segment[0].y.max(segment[1].y) + self.height as f32;
+            let center = player.get_center();
+            if center.x >= left && center.x < right && center.y >= top && center.y < bottom {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn draw(&mut self, context: &mut BarnContext, camera: &mut Camera, bgfx: &mut BarnGFX) {
+        let socket = context.load_texture(String::from("res/img/socket.png"));
+        socket.set_color_mod(170, 170, 170);
+        bgfx.sdl.draw_texture(socket, None, Some(Rect::new(
+            self.pos.x.round() as i32 - camera.x,
+            self.pos.y.round() as i32 - camera.y,
+            self.width,
+            self.height,
+        )));
+        // Glare in the direction of travel.
+        let step = self.get_step();
+        let pupil = context.load_texture(String::from("res/img/redpupil.png"));
+        bgfx.sdl.draw_texture(pupil, None, Some(Rect::new(
+            (self.pos.x + step.x * 12.0 / self.width as f32).round() as i32 - camera.x,
+            (self.pos.y + step.y * 6.0 / self.height as f32).round() as i32 - camera.y,
+            self.width,
+            self.height,
+        )));
+    }
+
+    fn get_step(&self) -> Vector2 {
+        let (width, height) = (self.width as f32, self.height as f32);
+        match self.direction.as_str() {
+            "left" => Vector2 { x: -width, y: 0.0 },
+            "right" => Vector2 { x: width, y: 0.0 },
+            "up" => Vector2 { x: 0.0, y: -height },
+            _ => Vector2 { x: 0.0, y: height },
+        }
+    }
+
+    fn get_next_tile(&self) -> Vector2 {
+        self.target_pos + self.get_step()
+    }
+
+    fn is_walkable(tiles: &Vec<Tile>, pos: Vector2) -> bool {
+        tiles.iter().any(|tile| tile.bb.origin == pos)
+            && !tiles.iter().any(|tile| tile.target_pos == pos && tile.blocks_player())
+    }
+}
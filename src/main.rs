@@ -2,6 +2,7 @@
 use barn::game::barn_context::BarnContext;
 use barn::game::game::Game;
 use barn::game::state::State;
+use game::audio;
 use game::start_menu_state::StartMenuState;
 
 mod game;
@@ -10,7 +11,12 @@ use crate::settings as config;
 
 pub fn main() -> Result<(), String> {
     // Create game window.
-    let mut game: Game = Game::new(&String::from(config::TITLE), 800, 600, false);
+    let settings = config::get();
+    let mut game: Game = Game::new(&String::from(config::TITLE), 
+        config::SCREEN_WIDTH * settings.window_scale, config::SCREEN_HEIGHT * settings.window_scale, settings.fullscreen);
+    settings.apply_window();
+    audio::init();
+    settings.apply_audio();
 
     // Create initial state and context.
     let state: Box<dyn State<BarnContext>> = Box::new(StartMenuState::new(0));
@@ -1,11 +1,187 @@
 use lazy_static::lazy_static;
 
 use barn::fonts::font_details::FontDetails;
+use crate::game::audio;
+use crate::game::controls::{get_default_bindings, Action, ACTIONS};
+
+use sdl2::keyboard::Keycode;
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::fs;
+use std::sync::Mutex;
 
 pub const DEBUG: bool = false;
 pub const TITLE: &str = "Mind's Eye";
-pub const ENABLE_SOUND: bool = true;
+pub const CONFIG_PATH: &str = "settings.cfg";
+pub const SCREEN_WIDTH: u32 = 800;
+pub const SCREEN_HEIGHT: u32 = 600;
+pub const MAX_VOLUME: u32 = 10;
+pub const MAX_WINDOW_SCALE: u32 = 3;
+const MAX_WINDOW_ID: u32 = 8;
+// Bounds on the key repeat timings, in milliseconds.
+pub const REPEAT_DELAY_RANGE: (u32, u32) = (50, 1000);
+pub const REPEAT_INTERVAL_RANGE: (u32, u32) = (40, 500);
 
 lazy_static! {
     pub static ref FONT_DETAILS: FontDetails = FontDetails{path: "res/fonts/VeniceClassic.ttf", size: 19};
+    pub static ref SETTINGS: Mutex<Settings> = Mutex::new(Settings::load());
+}
+
+// Player preferences, read from the config file at startup and written back
+// whenever they are changed.
+#[derive(Clone)]
+pub struct Settings {
+    pub master_volume: u32,
+    pub music_volume: u32,
+    pub sfx_volume: u32,
+    pub mute: bool,
+    pub fullscreen: bool,
+    pub window_scale: u32,
+    pub show_gaze: bool,
+    pub show_hud: bool,
+    pub lighting: bool,
+    pub challenge: bool,
+    pub grid_movement: bool,
+    pub repeat_delay: u32,
+    pub repeat_interval: u32,
+    pub bindings: HashMap<Action, Vec<Keycode>>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings {
+            master_volume: MAX_VOLUME,
+            music_volume: 7,
+            sfx_volume: MAX_VOLUME,
+            mute: false,
+            fullscreen: false,
+            window_scale: 1,
+            show_gaze: false,
+            show_hud: true,
+            lighting: true,
+            challenge: false,
+            grid_movement: false,
+            repeat_delay: 250,
+            repeat_interval: 120,
+            bindings: get_default_bindings(),
+        }
+    }
+
+    // Missing or unreadable values keep their defaults.
+    pub fn load() -> Self {
+        let mut settings = Settings::new();
+        let f = match fs::read_to_string(CONFIG_PATH) {
+            Ok(f) => f,
+            Err(_) => return settings,
+        };
+        for line in f.lines() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match key {
+                // Older config files only had the one volume.
+                "volume" | "master_volume" => settings.master_volume = value.parse().unwrap_or(settings.master_volume).min(MAX_VOLUME),
+                "music_volume" => settings.music_volume = value.parse().unwrap_or(settings.music_volume).min(MAX_VOLUME),
+                "sfx_volume" => settings.sfx_volume = value.parse().unwrap_or(settings.sfx_volume).min(MAX_VOLUME),
+                "mute" => settings.mute = value == "true",
+                "fullscreen" => settings.fullscreen = value == "true",
+                "window_scale" => settings.window_scale = value.parse().unwrap_or(1).max(1).min(MAX_WINDOW_SCALE),
+                "show_gaze" => settings.show_gaze = value == "true",
+                "show_hud" => settings.show_hud = value == "true",
+                "lighting" => settings.lighting = value == "true",
+                "challenge" => settings.challenge = value == "true",
+                "grid_movement" => settings.grid_movement = value == "true",
+                "repeat_delay" => settings.repeat_delay = value.parse().unwrap_or(settings.repeat_delay)
+                    .max(REPEAT_DELAY_RANGE.0).min(REPEAT_DELAY_RANGE.1),
+                "repeat_interval" => settings.repeat_interval = value.parse().unwrap_or(settings.repeat_interval)
+                    .max(REPEAT_INTERVAL_RANGE.0).min(REPEAT_INTERVAL_RANGE.1),
+                _ => {
+                    // Key bindings look like `bind.move_up=Up,W`.
+                    if let Some(action) = key.strip_prefix("bind.").and_then(Action::from_name) {
+                        let keys = value.split(',').filter_map(|name| Keycode::from_name(name.trim())).collect();
+                        settings.bindings.insert(action, keys);
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let mut contents = format!(
+            "master_volume={}\nmusic_volume={}\nsfx_volume={}\nmute={}\nfullscreen={}\nwindow_scale={}\nshow_gaze={}\n\
+            show_hud={}\nlighting={}\nchallenge={}\ngrid_movement={}\nrepeat_delay={}\nrepeat_interval={}\n",
+            self.master_volume, self.music_volume, self.sfx_volume, self.mute, self.fullscreen, self.window_scale,
+            self.show_gaze, self.show_hud, self.lighting, self.challenge, self.grid_movement, self.repeat_delay,
+            self.repeat_interval);
+        for action in ACTIONS.iter() {
+            let keys = self.bindings.get(action).cloned().unwrap_or_default();
+            let names: Vec<String> = keys.iter().map(|key| key.name()).collect();
+            contents.push_str(&format!("bind.{}={}\n", action.get_name(), names.join(",")));
+        }
+        if fs::write(CONFIG_PATH, contents).is_err() {
+            log::warn!("Could not save settings to {}", CONFIG_PATH);
+        }
+    }
+
+    // Push the audio settings to the mixer.
+    pub fn apply_audio(&self) {
+        audio::apply_volumes(self);
+    }
+
+    // Resize the game window, keeping the game drawn at its native resolution.
+    pub fn apply_window(&self) {
+        let window = match get_window() {
+            Some(window) => window,
+            None => return,
+        };
+        unsafe {
+            if self.fullscreen {
+                sdl2::sys::SDL_SetWindowFullscreen(window,
+                    sdl2::sys::SDL_WindowFlags::SDL_WINDOW_FULLSCREEN_DESKTOP as u32);
+            } else {
+                sdl2::sys::SDL_SetWindowFullscreen(window, 0);
+                sdl2::sys::SDL_SetWindowSize(window,
+                    (SCREEN_WIDTH * self.window_scale) as i32, (SCREEN_HEIGHT * self.window_scale) as i32);
+                sdl2::sys::SDL_SetWindowPosition(window,
+                    sdl2::sys::SDL_WINDOWPOS_CENTERED_MASK as i32, sdl2::sys::SDL_WINDOWPOS_CENTERED_MASK as i32);
+            }
+            let renderer = sdl2::sys::SDL_GetRenderer(window);
+            if !renderer.is_null() {
+                sdl2::sys::SDL_RenderSetLogicalSize(renderer, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
+            }
+        }
+    }
+}
+
+pub fn get() -> Settings {
+    SETTINGS.lock().unwrap().clone()
+}
+
+// Change the settings, then apply and save the result. The window is only
+// touched when its settings change, as applying them re-centres it.
+pub fn update<F: FnOnce(&mut Settings)>(change: F) {
+    let mut settings = SETTINGS.lock().unwrap();
+    let window = (settings.fullscreen, settings.window_scale);
+    change(&mut settings);
+    settings.apply_audio();
+    if (settings.fullscreen, settings.window_scale) != window {
+        settings.apply_window();
+    }
+    settings.save();
+}
+
+// The game's window. The engine doesn't hand it out, so look for the window
+// with the game's title among the first few window IDs SDL gives out.
+pub fn get_window() -> Option<*mut sdl2::sys::SDL_Window> {
+    (1..=MAX_WINDOW_ID).map(|id| unsafe { sdl2::sys::SDL_GetWindowFromID(id) }).find(|&window| {
+        !window.is_null() && unsafe { CStr::from_ptr(sdl2::sys::SDL_GetWindowTitle(window)) }.to_str() == Ok(TITLE)
+    })
+}
+
+// Whether sound effects can be heard at all.
+pub fn is_sound_enabled() -> bool {
+    let settings = SETTINGS.lock().unwrap();
+    !settings.mute && settings.master_volume > 0 && settings.sfx_volume > 0
 }